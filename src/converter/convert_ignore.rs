@@ -78,6 +78,8 @@ pub fn convert_content(content: &str, format: &str, source_path: &Path, dest_pat
     // Vector para almacenar patrones por grupo
     let mut current_group_name = String::from("default");
     let mut current_group_patterns = Vec::new();
+    // Nombres ya usados: un grupo no puede definirse dos veces
+    let mut used_group_names = std::collections::HashSet::new();
     let mut last_line_was_comment = false;
     let mut last_comment = String::new();
     
@@ -105,6 +107,7 @@ pub fn convert_content(content: &str, format: &str, source_path: &Path, dest_pat
         if last_line_was_comment && !trimmed.starts_with('#') {
             // Finalizar grupo actual si tiene patrones
             if !current_group_patterns.is_empty() {
+                used_group_names.insert(current_group_name.clone());
                 output_content.push_str(&format!("[{}] {{\n", current_group_name));
                 for pattern in &current_group_patterns {
                     output_content.push_str(&format!("    {}\n", pattern));
@@ -120,10 +123,16 @@ pub fn convert_content(content: &str, format: &str, source_path: &Path, dest_pat
             }
             
             // Crear nuevo grupo basado en el comentario
-            if !last_comment.is_empty() {
-                current_group_name = slugify(&last_comment);
+            let base_name = if !last_comment.is_empty() {
+                slugify(&last_comment)
             } else {
-                current_group_name = String::from("default");
+                String::from("default")
+            };
+            current_group_name = base_name.clone();
+            let mut suffix = 2;
+            while used_group_names.contains(&current_group_name) {
+                current_group_name = format!("{}_{}", base_name, suffix);
+                suffix += 1;
             }
         }
         
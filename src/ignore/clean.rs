@@ -0,0 +1,457 @@
+// Module: ignore/clean.rs
+//
+// This module removes ignored files from a directory tree, as `git clean -X`
+// does. A plan is built first so it can be shown before anything is touched;
+// directories kept by `&` patterns stay in place with their contents removed,
+// and nested repositories are left alone.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Context, Result};
+use walkdir::WalkDir;
+
+use super::tree::{PathTree, VCS_DIRECTORIES};
+use super::validate::DEFAULT_FILE_NAMES;
+use super::walk::{DirSummary, EntryStatus, WalkEntry};
+use super::DotIgnore;
+
+/// Rule files of other tools and formats a clean never removes, along with `DEFAULT_FILE_NAMES`
+const RULE_FILE_NAMES: &[&str] = &[".gitignore", ".svnignore", ".ignore.toml", ".ignore.yaml", ".ignore-lint.toml"];
+
+/// Which ignored files a clean removes; every condition must hold
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CleanFilter {
+    /// Only remove paths decided by one of these groups, any when empty
+    pub groups: Vec<String>,
+    /// Only remove files larger than this many bytes
+    pub larger_than: Option<u64>,
+    /// Only remove files last modified longer ago than this
+    pub older_than: Option<Duration>,
+    /// Paths relative to the rules' directory that are never removed
+    pub keep: Vec<PathBuf>,
+    /// File names of rule files that are never removed, such as a custom `--file`,
+    /// besides `.ignore`, `.DotIgnore`, `.gitignore` and the other usual names
+    pub rule_files: Vec<String>,
+}
+
+impl CleanFilter {
+    /// Check if the filter looks at file sizes or ages
+    fn has_predicates(&self) -> bool {
+        self.larger_than.is_some() || self.older_than.is_some()
+    }
+
+    /// Check if the group and keep conditions allow removing `entry`
+    fn allows(&self, entry: &WalkEntry) -> bool {
+        let group_ok = self.groups.is_empty()
+            || entry.group.as_ref().is_some_and(|group| self.groups.contains(group));
+        group_ok && !self.keep.contains(&entry.path) && !self.is_rule_file(entry)
+    }
+
+    /// Check if `entry` is a rule file, which a clean never removes
+    fn is_rule_file(&self, entry: &WalkEntry) -> bool {
+        let Some(name) = entry.path.file_name().and_then(|name| name.to_str()) else { return false };
+        !entry.is_dir() && (DEFAULT_FILE_NAMES.contains(&name) || RULE_FILE_NAMES.contains(&name)
+            || self.rule_files.iter().any(|rule_file| rule_file == name))
+    }
+}
+
+/// How a clean gets rid of files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CleanMode {
+    Delete,
+    /// Move to the trash of the desktop, so files can be restored
+    #[cfg(feature = "trash")]
+    Trash,
+}
+
+/// What a clean would remove, in tree order
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CleanPlan {
+    /// Files, and directories removed whole with their contents in `collapsed`
+    pub entries: Vec<WalkEntry>,
+}
+
+impl CleanPlan {
+    /// Number of files the plan removes, including those inside directories
+    pub fn files(&self) -> usize {
+        self.entries.iter().map(|entry| entry.collapsed.map_or(1, |summary| summary.files)).sum()
+    }
+
+    /// Bytes the plan frees
+    pub fn bytes(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.collapsed.map_or(entry.size.unwrap_or(0), |summary| summary.bytes)).sum()
+    }
+
+    /// Remove every entry of the plan, with paths relative to `root`, going on after failures
+    pub fn execute<P: AsRef<Path>>(&self, root: P, mode: CleanMode) -> CleanReport {
+        let root = root.as_ref();
+        let mut report = CleanReport { mode, ..Default::default() };
+
+        for entry in &self.entries {
+            match remove_entry(&root.join(&entry.path), entry.is_dir(), mode) {
+                Ok(removed) => {
+                    report.files += removed.files;
+                    report.bytes += removed.bytes;
+                    report.removed.push(WalkEntry { collapsed: entry.collapsed.map(|_| removed), ..entry.clone() });
+                }
+                Err(e) => report.failed.push(CleanFailure { path: entry.path.clone(), message: format!("{:#}", e) }),
+            }
+        }
+
+        report
+    }
+}
+
+/// A path a clean could not remove
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CleanFailure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Outcome of `CleanPlan::execute`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CleanReport {
+    pub mode: CleanMode,
+    /// Entries removed, in the order they were removed, with what was found in directories
+    pub removed: Vec<WalkEntry>,
+    pub failed: Vec<CleanFailure>,
+    /// Files actually removed, including those inside directories
+    pub files: usize,
+    /// Bytes actually freed
+    pub bytes: u64,
+}
+
+impl Default for CleanReport {
+    fn default() -> Self {
+        Self { mode: CleanMode::Delete, removed: Vec::new(), failed: Vec::new(), files: 0, bytes: 0 }
+    }
+}
+
+impl DotIgnore {
+    /// Plan the removal of the ignored files below `tree` that pass `filter`.
+    ///
+    /// `tree` must be `base`, the directory the rules apply from, or a directory
+    /// below it; paths are matched and planned relative to `base`.
+    /// An ignored directory is removed whole when everything in it would be;
+    /// directories preserved by `&` are kept, and only their files are removed.
+    /// Rule files such as `.ignore` or `.gitignore` are never removed, and
+    /// neither is anything in a directory holding a nested repository.
+    pub fn plan_clean<P: AsRef<Path>, Q: AsRef<Path>>(&self, base: P, tree: Q, filter: &CleanFilter) -> Result<CleanPlan> {
+        let root = base.as_ref();
+        let walk = self.walk(&PathTree::scan_within(root, tree)?);
+        let now = SystemTime::now();
+
+        // The scan skips version control metadata, so nested repositories are found here
+        let repositories: Vec<&Path> = walk.entries.iter()
+            .filter(|entry| entry.is_dir() && VCS_DIRECTORIES.iter().any(|name| root.join(&entry.path).join(name).is_dir()))
+            .map(|entry| entry.path.as_path())
+            .collect();
+        let in_repository = |entry: &WalkEntry| repositories.iter().any(|repository| entry.path.starts_with(repository));
+
+        let selected = |entry: &WalkEntry| -> Result<bool> {
+            if entry.status != EntryStatus::Ignored || !filter.allows(entry) || in_repository(entry) {
+                return Ok(false);
+            }
+            if filter.larger_than.is_some_and(|limit| entry.size.unwrap_or(0) <= limit) {
+                return Ok(false);
+            }
+            if let Some(age) = filter.older_than {
+                let path = root.join(&entry.path);
+                let modified = fs::metadata(&path).and_then(|metadata| metadata.modified())
+                    .with_context(|| format!("Failed to read the modification time of {}", path.display()))?;
+                return Ok(now.duration_since(modified).is_ok_and(|elapsed| elapsed > age));
+            }
+            Ok(true)
+        };
+
+        // De abajo arriba: un directorio se borra entero si todo su contenido se borraría
+        let mut contents: HashMap<&Path, (bool, DirSummary)> = HashMap::new();
+        let mut removable: HashMap<&Path, DirSummary> = HashMap::new();
+        let mut chosen: HashSet<&Path> = HashSet::new();
+        for entry in walk.entries.iter().rev() {
+            let (ok, summary) = match entry.size {
+                Some(size) => {
+                    let ok = selected(entry)?;
+                    if ok {
+                        chosen.insert(&entry.path);
+                    }
+                    (ok, DirSummary { files: 1, bytes: size })
+                }
+                None => {
+                    let (all, summary) = contents.remove(entry.path.as_path()).unwrap_or((true, DirSummary::default()));
+                    let ok = all && entry.status == EntryStatus::Ignored && filter.allows(entry) && !in_repository(entry)
+                        && (summary.files > 0 || !filter.has_predicates());
+                    if ok {
+                        removable.insert(&entry.path, summary);
+                    }
+                    (ok, summary)
+                }
+            };
+
+            let parent = entry.path.parent().unwrap_or(Path::new(""));
+            let (all, total) = contents.entry(parent).or_insert((true, DirSummary::default()));
+            *all &= ok;
+            total.files += summary.files;
+            total.bytes += summary.bytes;
+        }
+
+        let mut entries = Vec::new();
+        let mut removed_dir: Option<&Path> = None;
+        for entry in &walk.entries {
+            if removed_dir.is_some_and(|dir| entry.path.starts_with(dir)) {
+                continue;
+            }
+            if let Some(summary) = removable.get(entry.path.as_path()) {
+                removed_dir = Some(&entry.path);
+                entries.push(WalkEntry { collapsed: Some(*summary), ..entry.clone() });
+            } else if chosen.contains(entry.path.as_path()) {
+                entries.push(entry.clone());
+            }
+        }
+
+        Ok(CleanPlan { entries })
+    }
+}
+
+/// Remove one file or directory, or move it to the trash, and count the files that went with it.
+///
+/// A directory holding version control metadata at any depth is refused.
+fn remove_entry(path: &Path, is_dir: bool, mode: CleanMode) -> Result<DirSummary> {
+    let mut removed = DirSummary::default();
+    if is_dir {
+        for inner in WalkDir::new(path) {
+            let inner = inner?;
+            if !inner.file_type().is_dir() {
+                removed.files += 1;
+                removed.bytes += inner.metadata()?.len();
+            } else if VCS_DIRECTORIES.iter().any(|name| inner.file_name() == *name) {
+                bail!("{} holds a repository", inner.path().parent().unwrap_or(path).display());
+            }
+        }
+    } else {
+        removed = DirSummary { files: 1, bytes: fs::symlink_metadata(path)?.len() };
+    }
+
+    match mode {
+        CleanMode::Delete if is_dir => fs::remove_dir_all(path)?,
+        CleanMode::Delete => fs::remove_file(path)?,
+        #[cfg(feature = "trash")]
+        CleanMode::Trash => trash::delete(path).map_err(|e| anyhow!("{}", e))?,
+    }
+    Ok(removed)
+}
+
+/// Parse a size such as `500`, `10KB` or `2GB`, with the units of `size:` conditions
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| anyhow!("Invalid size '{}'", text))?;
+    let factor: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "KB" => 1024,
+        "MB" => 1024 * 1024,
+        "GB" => 1024 * 1024 * 1024,
+        _ => bail!("Invalid size unit in '{}'; use B, KB, MB or GB", text),
+    };
+    number.checked_mul(factor).ok_or_else(|| anyhow!("Size '{}' is too large", text))
+}
+
+/// Parse an age such as `90s`, `30m`, `12h`, `7d` or `2w`
+pub fn parse_age(text: &str) -> Result<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| anyhow!("Invalid age '{}'", text))?;
+    let seconds: u64 = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("Invalid age unit in '{}'; use s, m, h, d or w", text),
+    };
+    number.checked_mul(seconds).map(Duration::from_secs).ok_or_else(|| anyhow!("Age '{}' is too large", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+    use std::fs::File;
+
+    const RULES: &str = "*.log\n!keep.log\n[deps] {\n    node_modules/\n}\n[cache] {\n    &cache/\n}\n";
+
+    /// Create the files of `files` with the given sizes below a new directory
+    fn tree(files: &[(&str, usize)]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, size) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, vec![b'x'; *size]).unwrap();
+        }
+        dir
+    }
+
+    fn plan(root: &Path, filter: &CleanFilter) -> CleanPlan {
+        IgnoreParser::new().parse_string(RULES).unwrap().plan_clean(root, root, filter).unwrap()
+    }
+
+    fn paths(plan: &CleanPlan) -> Vec<&Path> {
+        plan.entries.iter().map(|entry| entry.path.as_path()).collect()
+    }
+
+    #[test]
+    fn test_plan_clean() {
+        let dir = tree(&[
+            ("node_modules/left-pad/index.js", 100), ("node_modules/react.js", 300), ("cache/sub/b.bin", 20),
+            ("logs/app.log", 5), ("logs/keep.log", 5), ("src/main.rs", 50),
+        ]);
+
+        let plan = plan(dir.path(), &CleanFilter::default());
+        assert_eq!(paths(&plan), ["cache/sub/b.bin", "logs/app.log", "node_modules"].map(Path::new));
+        assert_eq!(plan.entries[2].collapsed, Some(DirSummary { files: 2, bytes: 400 }));
+        assert_eq!((plan.files(), plan.bytes()), (4, 425));
+    }
+
+    #[test]
+    fn test_clean_subdirectory() {
+        let rules = "tmp/*\n";
+        let dir = tree(&[("project/tmp/important.txt", 5), ("tmp/scratch.txt", 5)]);
+        let root = dir.path();
+        let dotignore = IgnoreParser::new().parse_string(rules).unwrap();
+
+        // Paths are matched from the rules' directory, where project/tmp is not tmp/
+        let plan = dotignore.plan_clean(root, root.join("project"), &CleanFilter::default()).unwrap();
+        assert!(plan.entries.is_empty());
+
+        let plan = dotignore.plan_clean(root, root.join("tmp"), &CleanFilter::default()).unwrap();
+        assert_eq!(paths(&plan), [Path::new("tmp/scratch.txt")]);
+        plan.execute(root, CleanMode::Delete);
+        assert!(!root.join("tmp/scratch.txt").exists() && root.join("project/tmp/important.txt").exists());
+        assert!(dotignore.plan_clean(root.join("project"), root, &CleanFilter::default()).is_err());
+    }
+
+    #[test]
+    fn test_group_filter() {
+        let dir = tree(&[("node_modules/react.js", 300), ("cache/b.bin", 20), ("app.log", 5)]);
+
+        let filter = CleanFilter { groups: vec!["deps".to_string(), "cache".to_string()], ..Default::default() };
+        assert_eq!(paths(&plan(dir.path(), &filter)), ["cache/b.bin", "node_modules"].map(Path::new));
+    }
+
+    #[test]
+    fn test_size_filter() {
+        let dir = tree(&[("node_modules/left-pad/index.js", 100), ("node_modules/react.js", 300), ("big.log", 500)]);
+
+        // A directory is only removed whole when every file in it is large enough
+        let filter = CleanFilter { larger_than: Some(200), ..Default::default() };
+        assert_eq!(paths(&plan(dir.path(), &filter)), ["big.log", "node_modules/react.js"].map(Path::new));
+    }
+
+    #[test]
+    fn test_age_filter() {
+        let dir = tree(&[("old.log", 5), ("new.log", 5)]);
+        let week_ago = SystemTime::now() - Duration::from_secs(7 * 24 * 3600);
+        File::options().write(true).open(dir.path().join("old.log")).unwrap().set_modified(week_ago).unwrap();
+
+        let filter = CleanFilter { older_than: Some(Duration::from_secs(24 * 3600)), ..Default::default() };
+        assert_eq!(paths(&plan(dir.path(), &filter)), [Path::new("old.log")]);
+    }
+
+    #[test]
+    fn test_rule_files_are_kept() {
+        let rules = "*\n";
+        let dir = tree(&[
+            (".ignore", 0), ("app/.DotIgnore", 0), ("app/.gitignore", 0), ("app/main.rules", 0), ("app/x.o", 1),
+        ]);
+
+        let dotignore = IgnoreParser::new().parse_string(rules).unwrap();
+        let filter = CleanFilter { rule_files: vec!["main.rules".to_string()], ..Default::default() };
+        let plan = dotignore.plan_clean(dir.path(), dir.path(), &filter).unwrap();
+        assert_eq!(paths(&plan), [Path::new("app/x.o")]);
+    }
+
+    #[test]
+    fn test_delete() {
+        let dir = tree(&[("node_modules/react.js", 300), ("cache/sub/b.bin", 20), ("logs/keep.log", 5)]);
+        let root = dir.path();
+
+        let report = plan(root, &CleanFilter::default()).execute(root, CleanMode::Delete);
+        assert!(report.failed.is_empty());
+        assert_eq!((report.mode, report.files, report.bytes), (CleanMode::Delete, 2, 320));
+        assert!(!root.join("node_modules").exists());
+        // The preserved directory keeps its structure
+        assert!(root.join("cache/sub").is_dir() && !root.join("cache/sub/b.bin").exists());
+        assert!(root.join("logs/keep.log").exists());
+    }
+
+    #[cfg(all(feature = "trash", target_os = "linux"))]
+    #[test]
+    fn test_trash() {
+        let dir = tree(&[("node_modules/react.js", 300), ("data/.local/share/placeholder", 0)]);
+        let root = dir.path();
+        std::env::set_var("XDG_DATA_HOME", root.join("data/.local/share"));
+
+        let plan = plan(root, &CleanFilter::default());
+        let report = plan.execute(root, CleanMode::Trash);
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(report.files, 1);
+        assert!(!root.join("node_modules").exists());
+        assert!(root.join("data/.local/share/Trash/files/node_modules/react.js").exists());
+    }
+
+    #[test]
+    fn test_nested_repositories_are_kept() {
+        let rules = "vendor/\n";
+        let dir = tree(&[("vendor/a.o", 3), ("vendor/lib/x.c", 5), ("vendor/lib/.git/HEAD", 4), ("vendor/tools/b.o", 7)]);
+        let root = dir.path();
+        let dotignore = IgnoreParser::new().parse_string(rules).unwrap();
+
+        let plan = dotignore.plan_clean(root, root, &CleanFilter::default()).unwrap();
+        assert_eq!(paths(&plan), ["vendor/a.o", "vendor/tools"].map(Path::new));
+
+        let report = plan.execute(root, CleanMode::Delete);
+        assert_eq!((report.files, report.bytes), (2, 10));
+        assert!(root.join("vendor/lib/.git/HEAD").exists() && root.join("vendor/lib/x.c").exists());
+
+        // A repository that appears after planning is not removed either
+        fs::create_dir_all(root.join("vendor/tools/.svn")).unwrap();
+        let report = CleanPlan { entries: vec![plan.entries[1].clone()] }.execute(root, CleanMode::Delete);
+        assert_eq!(report.failed.len(), 1);
+        assert!(root.join("vendor/tools/.svn").exists());
+    }
+
+    #[test]
+    fn test_failures_are_reported() {
+        let dir = tree(&[("a.log", 5), ("b.log", 7)]);
+        let root = dir.path();
+        let plan = plan(root, &CleanFilter::default());
+        fs::remove_file(root.join("a.log")).unwrap();
+
+        // The other entries are still removed
+        let report = plan.execute(root, CleanMode::Delete);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].path, Path::new("a.log"));
+        assert_eq!(paths(&CleanPlan { entries: report.removed }), [Path::new("b.log")]);
+        assert_eq!((report.files, report.bytes), (1, 7));
+        assert!(!root.join("b.log").exists());
+    }
+
+    #[test]
+    fn test_parse_size_and_age() {
+        assert_eq!(parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("500").unwrap(), 500);
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 24 * 3600));
+        assert!(parse_size("10XB").is_err() && parse_age("3y").is_err());
+    }
+}
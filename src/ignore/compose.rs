@@ -0,0 +1,153 @@
+// Module: ignore/compose.rs
+//
+// This module resolves group composition: `[child] extends [parent]` headers
+// and `@use name` lines copy the patterns of other groups into a group, so
+// shared rules are written once.
+
+use anyhow::{anyhow, Result};
+
+use super::{Pattern, PatternGroup};
+
+/// A place in a group where the patterns of another group are inserted
+#[derive(Debug, Clone)]
+pub(crate) struct GroupReference {
+    /// Index of the referencing group
+    group: usize,
+    /// Number of the group's own patterns that come before the reference
+    position: usize,
+    /// Name of the referenced group
+    target: String,
+    /// 1-based line of the reference, used in errors
+    line: usize,
+}
+
+impl GroupReference {
+    pub(crate) fn new(group: usize, position: usize, target: &str, line: usize) -> Self {
+        Self {
+            group,
+            position,
+            target: target.to_string(),
+            line,
+        }
+    }
+}
+
+/// Replace every reference with the resolved patterns of the group it names.
+///
+/// References must be given in file order. Fails on unknown groups and on
+/// groups that reference themselves, directly or through other groups.
+pub(crate) fn resolve(groups: &mut [PatternGroup], references: &[GroupReference]) -> Result<()> {
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let mut resolver = Resolver {
+        groups,
+        references,
+        resolved: vec![None; groups.len()],
+        stack: Vec::new(),
+    };
+    for index in 0..groups.len() {
+        resolver.resolve(index)?;
+    }
+
+    let resolved = resolver.resolved;
+    for (group, patterns) in groups.iter_mut().zip(resolved) {
+        group.patterns = patterns.expect("every group is resolved");
+    }
+    Ok(())
+}
+
+struct Resolver<'a> {
+    groups: &'a [PatternGroup],
+    references: &'a [GroupReference],
+    resolved: Vec<Option<Vec<Pattern>>>,
+    /// Groups being resolved, outermost first
+    stack: Vec<usize>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, index: usize) -> Result<()> {
+        if self.resolved[index].is_some() {
+            return Ok(());
+        }
+
+        if let Some(start) = self.stack.iter().position(|&i| i == index) {
+            let cycle: Vec<&str> = self.stack[start..].iter()
+                .chain(std::iter::once(&index))
+                .map(|&i| self.groups[i].name.as_str())
+                .collect();
+            return Err(anyhow!("Cycle in group references: {}", cycle.join(" -> ")));
+        }
+
+        self.stack.push(index);
+
+        let groups = self.groups;
+        let own = &groups[index].patterns;
+        let mut patterns = Vec::new();
+        let mut copied = 0;
+
+        for reference in self.references.iter().filter(|r| r.group == index) {
+            let target = groups.iter()
+                .position(|group| group.name == reference.target)
+                .ok_or_else(|| anyhow!("Unknown group '{}' referenced at line {}", reference.target, reference.line))?;
+            self.resolve(target)?;
+
+            patterns.extend_from_slice(&own[copied..reference.position]);
+            copied = reference.position;
+            patterns.extend_from_slice(self.resolved[target].as_ref().expect("resolved above"));
+        }
+        patterns.extend_from_slice(&own[copied..]);
+
+        self.stack.pop();
+        self.resolved[index] = Some(patterns);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(name: &str, patterns: &[&str]) -> PatternGroup {
+        PatternGroup::new(name).with_patterns(patterns.iter().map(|p| Pattern::new(p)).collect())
+    }
+
+    fn pattern_texts(group: &PatternGroup) -> Vec<&str> {
+        group.patterns.iter().map(|p| p.pattern.as_str()).collect()
+    }
+
+    #[test]
+    fn test_resolve_in_place() {
+        let mut groups = vec![
+            group("all", &["*.tmp", "*.bak"]),
+            group("base", &["*.o"]),
+            group("build", &["*.a"]),
+        ];
+        let references = [
+            GroupReference::new(0, 1, "build", 5),
+            GroupReference::new(2, 0, "base", 8),
+        ];
+
+        resolve(&mut groups, &references).unwrap();
+        assert_eq!(pattern_texts(&groups[0]), ["*.tmp", "*.o", "*.a", "*.bak"]);
+        assert_eq!(pattern_texts(&groups[2]), ["*.o", "*.a"]);
+    }
+
+    #[test]
+    fn test_resolve_errors() {
+        let mut groups = vec![group("a", &[]), group("b", &[])];
+        let references = [
+            GroupReference::new(0, 0, "b", 1),
+            GroupReference::new(1, 0, "a", 4),
+        ];
+        let error = resolve(&mut groups, &references).unwrap_err();
+        assert_eq!(error.to_string(), "Cycle in group references: a -> b -> a");
+
+        let error = resolve(&mut groups, &[GroupReference::new(1, 0, "b", 4)]).unwrap_err();
+        assert_eq!(error.to_string(), "Cycle in group references: b -> b");
+
+        let error = resolve(&mut groups, &[GroupReference::new(0, 0, "missing", 2)]).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown group 'missing' referenced at line 2");
+    }
+}
@@ -23,6 +23,7 @@ lazy_static! {
 
 /// Represents the result of a conversion operation
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConversionResult {
     /// Source file that was converted
     pub source_file: PathBuf,
@@ -61,6 +62,26 @@ impl IgnoreConverter {
     
     /// Convert a file from one ignore format to DotIgnore
     pub fn convert_file(&self, source_path: &Path, destination_path: Option<&Path>) -> Result<ConversionResult> {
+        // Determine destination path
+        let dest_path = if let Some(path) = destination_path {
+            path.to_path_buf()
+        } else {
+            let parent = source_path.parent().unwrap_or(Path::new("."));
+            parent.join(".ignore")
+        };
+        
+        let (converted_content, result) = self.convert_to_string(source_path, &dest_path)?;
+        
+        // Write to the destination file
+        std::fs::write(&dest_path, converted_content)?;
+        
+        Ok(result)
+    }
+    
+    /// Convert a file from one ignore format to DotIgnore text without writing it.
+    ///
+    /// The result names `destination_path` as the file the text is meant for.
+    pub fn convert_to_string(&self, source_path: &Path, destination_path: &Path) -> Result<(String, ConversionResult)> {
         // Determine the source format
         let format = self.determine_format(source_path)?;
         
@@ -73,25 +94,14 @@ impl IgnoreConverter {
             IgnoreFormat::Svn => self.convert_from_svn(&content),
         };
         
-        // Determine destination path
-        let dest_path = if let Some(path) = destination_path {
-            path.to_path_buf()
-        } else {
-            let parent = source_path.parent().unwrap_or(Path::new("."));
-            parent.join(".ignore")
-        };
-        
-        // Write to the destination file
-        std::fs::write(&dest_path, converted_content)?;
-        
         // Return conversion result
-        Ok(ConversionResult {
+        Ok((converted_content, ConversionResult {
             source_file: source_path.to_path_buf(),
-            destination_file: dest_path,
+            destination_file: destination_path.to_path_buf(),
             pattern_count: stats.total_patterns,
             standard_patterns: stats.standard_patterns,
             platform_patterns: stats.platform_patterns,
-        })
+        }))
     }
     
     /// Convert all files in a directory
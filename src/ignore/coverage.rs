@@ -0,0 +1,167 @@
+// Module: ignore/coverage.rs
+//
+// This module counts how often each pattern and group matches while walking a
+// tree, so that rules which no longer match anything can be found and removed.
+
+use std::fmt;
+
+use super::tree::PathTree;
+use super::{DotIgnore, Pattern, PatternGroup};
+
+/// How much one pattern matched in a tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleCoverage {
+    /// Group the pattern belongs to, `None` for patterns outside groups
+    pub group: Option<String>,
+    /// The pattern as written
+    pub pattern: String,
+    /// Files and directories the pattern matches, whether or not it decided them
+    pub matched: usize,
+    /// Files the pattern decided: ignored ones, or re-included ones for a negation
+    pub files: usize,
+    /// Total size of those files in bytes
+    pub bytes: u64,
+}
+
+impl RuleCoverage {
+    /// Check if the pattern matched no path at all
+    pub fn is_unused(&self) -> bool {
+        self.matched == 0
+    }
+}
+
+impl fmt::Display for RuleCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.group {
+            Some(group) => write!(f, "[{}] {}", group, self.pattern),
+            None => f.write_str(&self.pattern),
+        }
+    }
+}
+
+/// How much the patterns of one group decided in a tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupCoverage {
+    pub name: String,
+    /// Files one of the group's patterns ignored
+    pub files: usize,
+    /// Total size of those files in bytes
+    pub bytes: u64,
+}
+
+/// Outcome of `DotIgnore::coverage`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CoverageReport {
+    /// Every pattern that applies on this platform, patterns outside groups first
+    pub rules: Vec<RuleCoverage>,
+    /// Active groups, those ignoring the most files first
+    pub groups: Vec<GroupCoverage>,
+    /// Number of files and directories evaluated
+    pub scanned: usize,
+}
+
+impl CoverageReport {
+    /// Patterns that matched no path in the tree
+    pub fn unused(&self) -> impl Iterator<Item = &RuleCoverage> {
+        self.rules.iter().filter(|rule| rule.is_unused())
+    }
+}
+
+impl DotIgnore {
+    /// Count, for every pattern of the active groups and outside groups, the
+    /// paths of `tree` it matches and the files it decides.
+    ///
+    /// Patterns of groups for other platforms or disabled profiles are left
+    /// out, since they cannot match here.
+    pub fn coverage(&self, tree: &PathTree) -> CoverageReport {
+        let mut rules: Vec<(&Pattern, RuleCoverage)> = self.patterns.iter()
+            .map(|pattern| (None::<&PatternGroup>, pattern))
+            .chain(self.active_groups().flat_map(|group| group.patterns.iter().map(move |pattern| (Some(group), pattern))))
+            .map(|(group, pattern)| {
+                let coverage = RuleCoverage {
+                    group: group.map(|group| group.name.clone()),
+                    pattern: pattern.original.clone(),
+                    matched: 0,
+                    files: 0,
+                    bytes: 0,
+                };
+                (pattern, coverage)
+            })
+            .collect();
+        let mut groups: Vec<GroupCoverage> = self.active_groups()
+            .map(|group| GroupCoverage { name: group.name.clone(), files: 0, bytes: 0 })
+            .collect();
+
+        for entry in &tree.entries {
+            let path = entry.path.to_string_lossy();
+            for (pattern, coverage) in &mut rules {
+                if pattern.matches_entry(&path, entry.size) {
+                    coverage.matched += 1;
+                }
+            }
+
+            let (Some(decision), Some(size)) = (self.matching_rule_with_size(&entry.path, entry.size), entry.size) else {
+                continue;
+            };
+            // Patterns are told apart by address, since the same text may appear in several groups
+            if let Some((_, coverage)) = rules.iter_mut().find(|(pattern, _)| std::ptr::eq(*pattern, decision.pattern)) {
+                coverage.files += 1;
+                coverage.bytes += size;
+            }
+            if let Some(group) = decision.group.filter(|_| decision.is_ignored()) {
+                if let Some(group) = groups.iter_mut().find(|coverage| coverage.name == group.name) {
+                    group.files += 1;
+                    group.bytes += size;
+                }
+            }
+        }
+
+        groups.sort_by(|a, b| b.files.cmp(&a.files).then(b.bytes.cmp(&a.bytes)));
+        CoverageReport {
+            rules: rules.into_iter().map(|(_, coverage)| coverage).collect(),
+            groups,
+            scanned: tree.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_coverage() {
+        let content = "*.log\n!keep.log\n*.tmp\n[build] {\n    target/\n}\n[docs] {\n    site/\n}\n";
+        let dotignore = IgnoreParser::new().parse_string(content).unwrap();
+        let tree = PathTree::from_listing("\
+target/
+target/app\t100
+target/lib.rlib\t50
+debug.log\t10
+keep.log\t5
+src/main.rs\t20
+").unwrap();
+
+        let report = dotignore.coverage(&tree);
+        assert_eq!(report.scanned, 6);
+        let summary: Vec<(String, usize, usize, u64)> = report.rules.iter()
+            .map(|rule| (rule.to_string(), rule.matched, rule.files, rule.bytes))
+            .collect();
+        assert_eq!(summary, [
+            ("*.log".to_string(), 2, 1, 10),
+            ("!keep.log".to_string(), 1, 1, 5),
+            ("*.tmp".to_string(), 0, 0, 0),
+            ("[build] target/".to_string(), 3, 2, 150),
+            ("[docs] site/".to_string(), 0, 0, 0),
+        ]);
+
+        let unused: Vec<String> = report.unused().map(ToString::to_string).collect();
+        assert_eq!(unused, ["*.tmp", "[docs] site/"]);
+        assert_eq!(report.groups[0], GroupCoverage { name: "build".to_string(), files: 2, bytes: 150 });
+        assert_eq!(report.groups[1].files, 0);
+    }
+}
@@ -0,0 +1,280 @@
+// Module: ignore/diff.rs
+//
+// This module compares two rule sets by meaning rather than by text: patterns
+// added, removed or moved between groups, groups renamed, and predicate
+// changes. Comments, blank lines, indentation and ordering are not reported.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::writer::split_inherited;
+use super::{DotIgnore, Pattern, PatternGroup};
+
+/// Minimum share of patterns two groups must have in common to count as a rename
+const RENAME_SIMILARITY: f64 = 0.5;
+
+/// A single difference between two rule sets.
+///
+/// `group` fields name the group in the new rule set, or `None` for patterns
+/// outside groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "change", rename_all = "snake_case"))]
+pub enum RuleChange {
+    /// A group only present in the new rule set
+    GroupAdded { group: String },
+    /// A group only present in the old rule set
+    GroupRemoved { group: String },
+    /// A group whose name changed while most of its patterns stayed
+    GroupRenamed { from: String, to: String },
+    /// A group whose `extends` parent changed
+    ParentChanged { group: String, from: Option<String>, to: Option<String> },
+    /// A group whose `@profile` list changed
+    ProfilesChanged { group: String, from: Vec<String>, to: Vec<String> },
+    /// A pattern only present in the new rule set
+    PatternAdded { group: Option<String>, pattern: String },
+    /// A pattern only present in the old rule set
+    PatternRemoved { group: Option<String>, pattern: String },
+    /// A pattern that moved to another group
+    PatternMoved { pattern: String, from: Option<String>, to: Option<String> },
+    /// A pattern for the same path whose negation, size or directory condition changed
+    PredicateChanged { group: Option<String>, from: String, to: String },
+}
+
+impl fmt::Display for RuleChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleChange::GroupAdded { group } => write!(f, "+ group [{}]", group),
+            RuleChange::GroupRemoved { group } => write!(f, "- group [{}]", group),
+            RuleChange::GroupRenamed { from, to } => write!(f, "~ group [{}] renamed to [{}]", from, to),
+            RuleChange::ParentChanged { group, from, to } => write!(
+                f, "~ group [{}] extends {} instead of {}",
+                group, to.as_deref().map_or("nothing".to_string(), |p| format!("[{}]", p)),
+                from.as_deref().map_or("nothing".to_string(), |p| format!("[{}]", p)),
+            ),
+            RuleChange::ProfilesChanged { group, from, to } => write!(
+                f, "~ group [{}] profiles: {} -> {}", group, profile_list(from), profile_list(to)
+            ),
+            RuleChange::PatternAdded { group, pattern } => write!(f, "+ {} {}", scope(group), pattern),
+            RuleChange::PatternRemoved { group, pattern } => write!(f, "- {} {}", scope(group), pattern),
+            RuleChange::PatternMoved { pattern, from, to } => write!(f, "~ {} moved from {} to {}", pattern, scope(from), scope(to)),
+            RuleChange::PredicateChanged { group, from, to } => write!(f, "~ {} {} -> {}", scope(group), from, to),
+        }
+    }
+}
+
+fn scope(group: &Option<String>) -> String {
+    match group {
+        Some(name) => format!("[{}]", name),
+        None => "(top level)".to_string(),
+    }
+}
+
+fn profile_list(profiles: &[String]) -> String {
+    if profiles.is_empty() {
+        "(none)".to_string()
+    } else {
+        profiles.join(", ")
+    }
+}
+
+/// Outcome of `DotIgnore::diff`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleSetDiff {
+    /// Every difference, group changes first
+    pub changes: Vec<RuleChange>,
+}
+
+impl RuleSetDiff {
+    /// Check if the rule sets mean the same thing
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for RuleSetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+/// Patterns of one scope: the top level or a single group
+struct Scope<'a> {
+    /// Name in the new rule set, or in the old one for removed groups
+    name: Option<String>,
+    old: Vec<&'a Pattern>,
+    new: Vec<&'a Pattern>,
+}
+
+impl DotIgnore {
+    /// Compare this rule set (the old one) with `new`
+    pub fn diff(&self, new: &DotIgnore) -> RuleSetDiff {
+        let mut changes = Vec::new();
+        let mut scopes = vec![Scope {
+            name: None,
+            old: self.patterns.iter().collect(),
+            new: new.patterns.iter().collect(),
+        }];
+
+        // Pair groups by name, then unpaired groups by content
+        let mut pairs: Vec<(Option<&PatternGroup>, Option<&PatternGroup>)> = Vec::new();
+        let mut unpaired_new: Vec<&PatternGroup> = new.groups.iter()
+            .filter(|g| !self.groups.iter().any(|o| o.name == g.name))
+            .collect();
+        for old_group in &self.groups {
+            if let Some(new_group) = new.groups.iter().find(|g| g.name == old_group.name) {
+                pairs.push((Some(old_group), Some(new_group)));
+                continue;
+            }
+
+            let old_patterns = own_patterns(old_group, &self.groups);
+            let best = unpaired_new.iter().enumerate()
+                .map(|(i, g)| (i, similarity(&old_patterns, &own_patterns(g, &new.groups))))
+                .filter(|(_, score)| *score >= RENAME_SIMILARITY)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            match best {
+                Some((index, _)) => {
+                    let new_group = unpaired_new.remove(index);
+                    changes.push(RuleChange::GroupRenamed { from: old_group.name.clone(), to: new_group.name.clone() });
+                    pairs.push((Some(old_group), Some(new_group)));
+                }
+                None => {
+                    changes.push(RuleChange::GroupRemoved { group: old_group.name.clone() });
+                    pairs.push((Some(old_group), None));
+                }
+            }
+        }
+        for new_group in unpaired_new {
+            changes.push(RuleChange::GroupAdded { group: new_group.name.clone() });
+            pairs.push((None, Some(new_group)));
+        }
+
+        for (old_group, new_group) in pairs {
+            if let (Some(old_group), Some(new_group)) = (old_group, new_group) {
+                let old_parent = split_inherited(old_group, &self.groups).0.map(str::to_string);
+                let new_parent = split_inherited(new_group, &new.groups).0.map(str::to_string);
+                if old_parent != new_parent {
+                    changes.push(RuleChange::ParentChanged { group: new_group.name.clone(), from: old_parent, to: new_parent });
+                }
+                if old_group.profiles != new_group.profiles {
+                    changes.push(RuleChange::ProfilesChanged {
+                        group: new_group.name.clone(),
+                        from: old_group.profiles.clone(),
+                        to: new_group.profiles.clone(),
+                    });
+                }
+            }
+
+            scopes.push(Scope {
+                name: new_group.or(old_group).map(|g| g.name.clone()),
+                old: old_group.map(|g| own_patterns(g, &self.groups)).unwrap_or_default(),
+                new: new_group.map(|g| own_patterns(g, &new.groups)).unwrap_or_default(),
+            });
+        }
+
+        changes.extend(pattern_changes(&scopes));
+        RuleSetDiff { changes }
+    }
+}
+
+/// Patterns written in the group itself, without those inherited through `extends`
+fn own_patterns<'a>(group: &'a PatternGroup, groups: &[PatternGroup]) -> Vec<&'a Pattern> {
+    split_inherited(group, groups).1.iter().collect()
+}
+
+/// Share of patterns two groups have in common (Jaccard index)
+fn similarity(a: &[&Pattern], b: &[&Pattern]) -> f64 {
+    let a: HashSet<&str> = a.iter().map(|p| p.original.trim()).collect();
+    let b: HashSet<&str> = b.iter().map(|p| p.original.trim()).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Path a pattern applies to, without its negation, size condition or trailing slash
+fn path_key(pattern: &Pattern) -> &str {
+    pattern.pattern.trim().trim_end_matches('/')
+}
+
+fn pattern_changes(scopes: &[Scope]) -> Vec<RuleChange> {
+    let contains = |patterns: &[&Pattern], pattern: &Pattern| patterns.iter().any(|p| p.original.trim() == pattern.original.trim());
+
+    let mut removed: Vec<(&Option<String>, &Pattern)> = Vec::new();
+    let mut added: Vec<(&Option<String>, &Pattern)> = Vec::new();
+    for scope in scopes {
+        removed.extend(scope.old.iter().filter(|p| !contains(&scope.new, p)).map(|p| (&scope.name, *p)));
+        added.extend(scope.new.iter().filter(|p| !contains(&scope.old, p)).map(|p| (&scope.name, *p)));
+    }
+
+    let mut changes = Vec::new();
+    let mut removals = Vec::new();
+    for (group, pattern) in removed {
+        // The same text elsewhere is a move
+        if let Some(index) = added.iter().position(|(_, p)| p.original.trim() == pattern.original.trim()) {
+            let (to, _) = added.remove(index);
+            changes.push(RuleChange::PatternMoved { pattern: pattern.original.trim().to_string(), from: group.clone(), to: to.clone() });
+            continue;
+        }
+
+        // The same path in the same scope is a predicate change
+        if let Some(index) = added.iter().position(|(g, p)| *g == group && path_key(p) == path_key(pattern)) {
+            let (_, new_pattern) = added.remove(index);
+            changes.push(RuleChange::PredicateChanged {
+                group: group.clone(),
+                from: pattern.original.trim().to_string(),
+                to: new_pattern.original.trim().to_string(),
+            });
+            continue;
+        }
+
+        removals.push(RuleChange::PatternRemoved { group: group.clone(), pattern: pattern.original.trim().to_string() });
+    }
+
+    changes.extend(removals);
+    changes.extend(added.into_iter().map(|(group, pattern)| RuleChange::PatternAdded {
+        group: group.clone(),
+        pattern: pattern.original.trim().to_string(),
+    }));
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    fn parse(content: &str) -> DotIgnore {
+        IgnoreParser::new().parse_string(content).unwrap()
+    }
+
+    #[test]
+    fn test_formatting_is_ignored() {
+        let old = parse("*.tmp\n[build] {\n    target/\n    *.o\n}\n");
+        let new = parse("# Temporary files\n*.tmp\n\n[build] {\n  *.o\n  target/\n} # end build\n");
+        assert!(old.diff(&new).is_empty());
+    }
+
+    #[test]
+    fn test_pattern_and_group_changes() {
+        let old = parse("#! dotignore 1.3\n*.log\n[build] {\n    target/\n    *.o\n    *.a\n    size:>10MB *.bin\n}\n[editor] {\n    .idea/\n}\n");
+        let new = parse("#! dotignore 1.3\n[compile] {\n    @profile ci\n    target/\n    *.o\n    *.a\n    size:>50MB *.bin\n}\n[logs] {\n    *.log\n}\n[editor] {\n    .idea/\n    !.idea/shared/\n}\n");
+
+        let diff = old.diff(&new);
+        let compile = Some("compile".to_string());
+        assert_eq!(diff.changes, [
+            RuleChange::GroupRenamed { from: "build".into(), to: "compile".into() },
+            RuleChange::GroupAdded { group: "logs".into() },
+            RuleChange::ProfilesChanged { group: "compile".into(), from: vec![], to: vec!["ci".into()] },
+            RuleChange::PatternMoved { pattern: "*.log".into(), from: None, to: Some("logs".into()) },
+            RuleChange::PredicateChanged { group: compile, from: "size:>10MB *.bin".into(), to: "size:>50MB *.bin".into() },
+            RuleChange::PatternAdded { group: Some("editor".into()), pattern: "!.idea/shared/".into() },
+        ]);
+        assert_eq!(diff.changes[3].to_string(), "~ *.log moved from (top level) to [logs]");
+    }
+}
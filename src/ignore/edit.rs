@@ -0,0 +1,462 @@
+// Module: ignore/edit.rs
+//
+// This module implements programmatic editing of .DotIgnore files on top of the
+// lossless syntax tree, so comments and formatting survive a save.
+
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+use super::names;
+use super::parser::{IgnoreParser, GROUP_END_RE, GROUP_START_RE, USE_DIRECTIVE_RE};
+use super::syntax::{GroupNode, IgnoreDocument, LineEnding, SyntaxLine, SyntaxNode};
+
+/// Indentation used for new pattern lines when the file gives no example
+const DEFAULT_INDENT: &str = "    ";
+
+impl IgnoreDocument {
+    /// Load a .DotIgnore file as a syntax tree
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open file: {:?}", path))?;
+        IgnoreParser::new().read_document(BufReader::new(file), &path.display().to_string())
+    }
+
+    /// Write the syntax tree back to disk
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string())
+            .with_context(|| format!("Failed to write file: {:?}", path))
+    }
+
+    /// Find a group by name
+    pub fn group(&self, name: &str) -> Option<&GroupNode> {
+        self.groups().find(|group| group.name == name)
+    }
+
+    /// Find a group by name for editing
+    pub fn group_mut(&mut self, name: &str) -> Option<&mut GroupNode> {
+        self.nodes.iter_mut().find_map(|node| match node {
+            SyntaxNode::Group(group) if group.name == name => Some(group),
+            _ => None,
+        })
+    }
+
+    /// Add a pattern at the end of a group, creating the group if needed.
+    ///
+    /// Returns `false` if the group already contained the pattern.
+    pub fn add_pattern_to_group(&mut self, group_name: &str, pattern: &str) -> Result<bool> {
+        let pattern = validate_pattern(pattern)?;
+        let ending = self.line_ending();
+
+        if self.group(group_name).is_none() {
+            self.create_group_after(group_name, None)?;
+        }
+
+        let group = self.group_mut(group_name).expect("group was just created");
+        if group.body.iter().any(|node| is_pattern(node, pattern)) {
+            return Ok(false);
+        }
+
+        let indent = group.body.iter()
+            .find_map(|node| match node {
+                SyntaxNode::Pattern(line) | SyntaxNode::Directive(line) => Some(line.indent().to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| DEFAULT_INDENT.to_string());
+
+        // Insert after the last rule so trailing blank lines stay before the brace
+        let position = group.body.iter()
+            .rposition(|node| matches!(node, SyntaxNode::Pattern(_) | SyntaxNode::Directive(_)))
+            .map(|i| i + 1)
+            .unwrap_or(group.body.len());
+
+        // An unterminated previous line was the last line of the file; the new one takes its place
+        let previous = if position == 0 {
+            &mut group.header
+        } else {
+            last_line_mut(&mut group.body[position - 1])
+        };
+        let new_ending = if previous.ending == LineEnding::None { LineEnding::None } else { ending };
+        terminate(previous, ending);
+
+        let line = SyntaxLine::new(format!("{}{}", indent, pattern), new_ending);
+        group.body.insert(position, SyntaxNode::Pattern(line));
+        Ok(true)
+    }
+
+    /// Remove a pattern from one group, or from the whole file when `group_name` is `None`.
+    ///
+    /// Returns the number of lines removed.
+    pub fn remove_pattern(&mut self, pattern: &str, group_name: Option<&str>) -> usize {
+        let pattern = pattern.trim();
+        let mut removed = 0;
+
+        for node in &mut self.nodes {
+            if let SyntaxNode::Group(group) = node {
+                if group_name.is_none_or(|name| name == group.name) {
+                    let before = group.body.len();
+                    group.body.retain(|node| !is_pattern(node, pattern));
+                    removed += before - group.body.len();
+                }
+            }
+        }
+
+        if group_name.is_none() {
+            let before = self.nodes.len();
+            self.nodes.retain(|node| !is_pattern(node, pattern));
+            removed += before - self.nodes.len();
+        }
+
+        removed
+    }
+
+    /// Remove the pattern lines at the given 1-based line numbers
+    pub(crate) fn remove_lines(&mut self, lines: &[usize]) {
+        let mut line_number = 1;
+        self.nodes.retain_mut(|node| {
+            let start = line_number;
+            line_number += node.line_count();
+            match node {
+                SyntaxNode::Group(group) => {
+                    let mut child_line = start + 1;
+                    group.body.retain(|child| {
+                        child_line += 1;
+                        !(matches!(child, SyntaxNode::Pattern(_)) && lines.contains(&(child_line - 1)))
+                    });
+                    true
+                }
+                SyntaxNode::Pattern(_) => !lines.contains(&start),
+                _ => true,
+            }
+        });
+    }
+
+    /// Move a pattern from `from_group` into another group, creating it if needed.
+    ///
+    /// Without `from_group` the pattern is taken from the one group, or from
+    /// outside groups, where it is written; a pattern written in several places
+    /// is an error. The document is left unchanged when the move fails.
+    pub fn move_pattern(&mut self, pattern: &str, from_group: Option<&str>, to_group: &str) -> Result<()> {
+        let pattern = validate_pattern(pattern)?;
+        validate_group_name(to_group)?;
+
+        // `None` stands for the lines outside groups
+        let sources: Vec<Option<&str>> = self.nodes.iter()
+            .filter_map(|node| match node {
+                SyntaxNode::Group(group) if group.body.iter().any(|node| is_pattern(node, pattern)) => Some(Some(group.name.as_str())),
+                node if is_pattern(node, pattern) => Some(None),
+                _ => None,
+            })
+            .filter(|source| from_group.is_none() || *source == from_group)
+            .collect();
+        let source = match sources.as_slice() {
+            [] => match from_group {
+                Some(group) => return Err(anyhow!("Pattern not found in group {}: {}", group, pattern)),
+                None => return Err(anyhow!("Pattern not found: {}", pattern)),
+            },
+            [source] => source.map(String::from),
+            _ => {
+                let places: Vec<String> = sources.iter().map(|source| source.map_or("outside groups".to_string(), |name| format!("[{}]", name))).collect();
+                return Err(anyhow!("Pattern {} is in several places ({}); choose the group to move it from", pattern, places.join(", ")));
+            }
+        };
+
+        if self.group(to_group).is_none() {
+            self.create_group_after(to_group, None)?;
+        }
+        match &source {
+            Some(group) => {
+                self.remove_pattern(pattern, Some(group));
+            }
+            None => self.nodes.retain(|node| !is_pattern(node, pattern)),
+        }
+        self.add_pattern_to_group(to_group, pattern)?;
+        Ok(())
+    }
+
+    /// Rename a group, updating its `# end name` marker and the `extends` and
+    /// `@use` references to it as well
+    pub fn rename_group(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        validate_group_name(new_name)?;
+        if self.group(new_name).is_some() {
+            return Err(anyhow!("Group already exists: {}", new_name));
+        }
+
+        let group = self.group_mut(old_name)
+            .ok_or_else(|| anyhow!("Group not found: {}", old_name))?;
+
+        let old_header = format!("[{}]", names::quote(old_name));
+        let new_header = format!("[{}]", names::quote(new_name));
+        group.name = new_name.to_string();
+        if group.header.text.contains(&old_header) {
+            group.header.text = group.header.text.replacen(&old_header, &new_header, 1);
+        } else {
+            group.header.text = format!("{}{}", group.header.indent(), group.canonical_header());
+        }
+
+        if let Some(end) = &mut group.end {
+            let marker = GROUP_END_RE.captures(&end.text)
+                .and_then(|captures| captures.get(1))
+                .filter(|name| names::parse_group_name(name.as_str()).is_ok_and(|name| name == old_name))
+                .map(|name| name.range());
+            if let Some(range) = marker {
+                end.text.replace_range(range, &names::quote(new_name));
+            }
+        }
+
+        for node in &mut self.nodes {
+            if let SyntaxNode::Group(group) = node {
+                rename_references(group, old_name, new_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Create an empty group after `after`, or at the end of the file when `after` is `None`
+    pub fn create_group_after(&mut self, name: &str, after: Option<&str>) -> Result<()> {
+        validate_group_name(name)?;
+        if self.group(name).is_some() {
+            return Err(anyhow!("Group already exists: {}", name));
+        }
+
+        let position = match after {
+            Some(after) => self.nodes.iter()
+                .position(|node| matches!(node, SyntaxNode::Group(group) if group.name == after))
+                .map(|i| i + 1)
+                .ok_or_else(|| anyhow!("Group not found: {}", after))?,
+            None => self.nodes.len(),
+        };
+
+        let ending = self.line_ending();
+        let is_last = position == self.nodes.len();
+        if position > 0 {
+            terminate(last_line_mut(&mut self.nodes[position - 1]), ending);
+        }
+
+        let mut group = GroupNode {
+            name: name.to_string(),
+            parent: None,
+            header: SyntaxLine::new("", ending),
+            body: Vec::new(),
+            end: Some(SyntaxLine::new("}", ending)),
+        };
+        group.header.text = group.canonical_header();
+
+        let mut new_nodes = Vec::new();
+        if position > 0 && !matches!(self.nodes[position - 1], SyntaxNode::Blank(_)) {
+            new_nodes.push(SyntaxNode::Blank(SyntaxLine::new("", ending)));
+        }
+        new_nodes.push(SyntaxNode::Group(group));
+        if !is_last && !matches!(self.nodes[position], SyntaxNode::Blank(_)) {
+            new_nodes.push(SyntaxNode::Blank(SyntaxLine::new("", ending)));
+        }
+
+        self.nodes.splice(position..position, new_nodes);
+        Ok(())
+    }
+
+    /// Line ending used by the document, defaulting to `\n`
+    pub(crate) fn line_ending(&self) -> LineEnding {
+        let mut lines = Vec::new();
+        for node in &self.nodes {
+            collect_lines(node, &mut lines);
+            if let Some(line) = lines.iter().find(|line| line.ending != LineEnding::None) {
+                return line.ending;
+            }
+        }
+        LineEnding::Lf
+    }
+}
+
+fn collect_lines<'a>(node: &'a SyntaxNode, lines: &mut Vec<&'a SyntaxLine>) {
+    match node {
+        SyntaxNode::Group(group) => {
+            lines.push(&group.header);
+            for node in &group.body {
+                collect_lines(node, lines);
+            }
+            lines.extend(group.end.iter());
+        }
+        SyntaxNode::Blank(line)
+        | SyntaxNode::Comment(line)
+        | SyntaxNode::Directive(line)
+        | SyntaxNode::Pattern(line)
+        | SyntaxNode::UnexpectedEnd(line) => lines.push(line),
+    }
+}
+
+/// Last physical line of a node
+fn last_line_mut(node: &mut SyntaxNode) -> &mut SyntaxLine {
+    match node {
+        SyntaxNode::Group(group) => {
+            if let Some(end) = &mut group.end {
+                end
+            } else if let Some(last) = group.body.last_mut() {
+                last_line_mut(last)
+            } else {
+                &mut group.header
+            }
+        }
+        SyntaxNode::Blank(line)
+        | SyntaxNode::Comment(line)
+        | SyntaxNode::Directive(line)
+        | SyntaxNode::Pattern(line)
+        | SyntaxNode::UnexpectedEnd(line) => line,
+    }
+}
+
+/// Give a line a terminator if it was the unterminated last line of the file
+fn terminate(line: &mut SyntaxLine, ending: LineEnding) {
+    if line.ending == LineEnding::None {
+        line.ending = ending;
+    }
+}
+
+fn is_pattern(node: &SyntaxNode, pattern: &str) -> bool {
+    matches!(node, SyntaxNode::Pattern(line) if line.trimmed() == pattern)
+}
+
+/// Point the `extends` clause and `@use` lines of `group` at a renamed group
+fn rename_references(group: &mut GroupNode, old_name: &str, new_name: &str) {
+    if group.parent.as_deref() == Some(old_name) {
+        group.parent = Some(new_name.to_string());
+        let old_reference = format!("[{}]", names::quote(old_name));
+        match group.header.text.rfind(&old_reference) {
+            Some(index) => {
+                group.header.text.replace_range(index..index + old_reference.len(), &format!("[{}]", names::quote(new_name)));
+            }
+            None => group.header.text = format!("{}{}", group.header.indent(), group.canonical_header()),
+        }
+    }
+
+    for node in &mut group.body {
+        let SyntaxNode::Directive(line) = node else { continue };
+        let Some(list) = USE_DIRECTIVE_RE.captures(&line.text).map(|captures| captures[1].to_string()) else { continue };
+
+        let Ok(targets) = names::parse_group_name_list(&list) else { continue };
+        if targets.iter().any(|name| name == old_name) {
+            let targets: Vec<String> = targets.iter()
+                .map(|name| names::quote(if name == old_name { new_name } else { name }))
+                .collect();
+            line.text = format!("{}@use {}", line.indent(), targets.join(", "));
+        }
+    }
+}
+
+fn validate_pattern(pattern: &str) -> Result<&str> {
+    let pattern = pattern.trim();
+    if pattern.is_empty()
+        || pattern.starts_with('#')
+        || pattern.starts_with('@')
+        || pattern.contains('\n')
+        || GROUP_START_RE.is_match(pattern)
+        || GROUP_END_RE.is_match(pattern)
+    {
+        return Err(anyhow!("Invalid pattern: {:?}", pattern));
+    }
+    Ok(pattern)
+}
+
+fn validate_group_name(name: &str) -> Result<()> {
+    names::validate_group_name(name).map_err(|e| anyhow!("Invalid group name: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "# Project rules\n\n[build] {\n  # artifacts\n  *.o\n\n} # end build\n\n[logs] {\n    *.log\n}\n";
+
+    #[test]
+    fn test_add_pattern_preserves_formatting() {
+        let mut document = IgnoreDocument::parse(CONTENT);
+
+        assert!(document.add_pattern_to_group("build", "*.pyc").unwrap());
+        assert!(!document.add_pattern_to_group("build", "*.pyc").unwrap());
+
+        assert_eq!(
+            document.to_string(),
+            "# Project rules\n\n[build] {\n  # artifacts\n  *.o\n  *.pyc\n\n} # end build\n\n[logs] {\n    *.log\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_add_pattern_to_new_group() {
+        let mut document = IgnoreDocument::parse("*.tmp");
+        document.add_pattern_to_group("cache", ".cache/").unwrap();
+
+        assert_eq!(document.to_string(), "*.tmp\n\n[cache] {\n    .cache/\n}\n");
+    }
+
+    #[test]
+    fn test_remove_and_move_pattern() {
+        let mut document = IgnoreDocument::parse(CONTENT);
+
+        assert_eq!(document.remove_pattern("*.log", Some("build")), 0);
+        document.move_pattern("*.log", None, "build").unwrap();
+        assert!(document.move_pattern("missing", None, "build").is_err());
+
+        let dotignore = document.to_dotignore().unwrap();
+        let build = dotignore.groups.iter().find(|g| g.name == "build").unwrap();
+        let logs = dotignore.groups.iter().find(|g| g.name == "logs").unwrap();
+        assert_eq!(build.patterns.len(), 2);
+        assert!(logs.patterns.is_empty());
+    }
+
+    #[test]
+    fn test_move_pattern_from_one_group() {
+        let content = "[a] {\n    *.o\n}\n[b] {\n    *.o\n}\n";
+        let mut document = IgnoreDocument::parse(content);
+
+        // Ambiguous sources and invalid destinations leave the document alone
+        assert!(document.move_pattern("*.o", None, "c").is_err());
+        assert!(document.move_pattern("*.o", Some("a"), "  ").is_err());
+        assert!(document.move_pattern("*.o", Some("c"), "b").is_err());
+        assert_eq!(document.to_string(), content);
+
+        document.move_pattern("*.o", Some("b"), "a").unwrap();
+        assert_eq!(document.to_string(), "[a] {\n    *.o\n}\n[b] {\n}\n");
+    }
+
+    #[test]
+    fn test_rename_group() {
+        let mut document = IgnoreDocument::parse(CONTENT);
+
+        document.rename_group("build", "build_artifacts").unwrap();
+        assert!(document.rename_group("logs", "build_artifacts").is_err());
+        assert!(document.rename_group("logs", "platform:beos").is_err());
+        assert!(document.rename_group("logs", "").is_err());
+        document.rename_group("logs", "log files").unwrap();
+
+        let output = document.to_string();
+        assert!(output.contains("[build_artifacts] {\n"));
+        assert!(output.contains("} # end build_artifacts\n"));
+        assert!(output.contains("[\"log files\"] {\n"));
+
+        let mut document = IgnoreDocument::parse("[base] {\n}\n[debug]  extends [base] {\n    @use base,logs\n}\n");
+        document.rename_group("base", "common").unwrap();
+        assert_eq!(document.to_string(), "[common] {\n}\n[debug]  extends [common] {\n    @use common, logs\n}\n");
+    }
+
+    #[test]
+    fn test_add_pattern_to_unclosed_group() {
+        let mut document = IgnoreDocument::parse("[a] {\n    x\n[b] {\n    y");
+        document.add_pattern_to_group("a", "z").unwrap();
+        document.add_pattern_to_group("b", "w").unwrap();
+
+        assert_eq!(document.to_string(), "[a] {\n    x\n    z\n[b] {\n    y\n    w");
+    }
+
+    #[test]
+    fn test_create_group_after() {
+        let mut document = IgnoreDocument::parse("[a] {\r\n}\r\n[c] {\r\n}");
+        document.create_group_after("b", Some("a")).unwrap();
+        assert!(document.create_group_after("d", Some("missing")).is_err());
+
+        assert_eq!(document.to_string(), "[a] {\r\n}\r\n\r\n[b] {\r\n}\r\n\r\n[c] {\r\n}");
+    }
+}
@@ -0,0 +1,109 @@
+// Module: ignore/equiv.rs
+//
+// This module checks that two rule sets give the same verdict for every path
+// in a tree, as proof that a refactoring or conversion changed nothing.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use super::tree::PathTree;
+use super::DotIgnore;
+
+/// A path the two rule sets disagree on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Counterexample {
+    /// Path relative to the tree root, with `/` separators
+    pub path: PathBuf,
+    /// Whether the path is a directory
+    pub is_dir: bool,
+    /// Whether the first rule set ignores the path (the second does the opposite)
+    pub left_ignored: bool,
+    /// Pattern of the first rule set that decided, `None` if nothing matched
+    pub left_rule: Option<String>,
+    /// Pattern of the second rule set that decided, `None` if nothing matched
+    pub right_rule: Option<String>,
+}
+
+impl fmt::Display for Counterexample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = |ignored: bool| if ignored { "ignored" } else { "tracked" };
+        write!(
+            f,
+            "{}{}: {} by {}, {} by {}",
+            self.path.display(),
+            if self.is_dir { "/" } else { "" },
+            verdict(self.left_ignored),
+            self.left_rule.as_deref().unwrap_or("no rule"),
+            verdict(!self.left_ignored),
+            self.right_rule.as_deref().unwrap_or("no rule"),
+        )
+    }
+}
+
+/// Outcome of `DotIgnore::equivalent_on`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EquivalenceReport {
+    /// Paths with different verdicts, in tree order
+    pub counterexamples: Vec<Counterexample>,
+    /// Number of paths compared
+    pub checked: usize,
+}
+
+impl EquivalenceReport {
+    /// Check if the rule sets agreed on every path
+    pub fn is_equivalent(&self) -> bool {
+        self.counterexamples.is_empty()
+    }
+}
+
+impl DotIgnore {
+    /// Compare the verdicts of these rules and `other` for every path in `tree`.
+    ///
+    /// File sizes come from the tree, so size conditions are decided the same
+    /// way for real and virtual trees.
+    pub fn equivalent_on(&self, other: &DotIgnore, tree: &PathTree) -> EquivalenceReport {
+        let mut report = EquivalenceReport { checked: tree.len(), ..Default::default() };
+
+        for entry in &tree.entries {
+            let left = self.matching_rule_with_size(&entry.path, entry.size);
+            let right = other.matching_rule_with_size(&entry.path, entry.size);
+            let left_ignored = left.is_some_and(|rule| rule.is_ignored());
+            if left_ignored == right.is_some_and(|rule| rule.is_ignored()) {
+                continue;
+            }
+
+            report.counterexamples.push(Counterexample {
+                path: entry.path.clone(),
+                is_dir: entry.is_dir(),
+                left_ignored,
+                left_rule: left.map(|rule| rule.to_string()),
+                right_rule: right.map(|rule| rule.to_string()),
+            });
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_equivalent_on() {
+        let parser = IgnoreParser::new();
+        let flat = parser.parse_string("*.log\ntarget/\nsize:>1MB *.bin\n").unwrap();
+        let grouped = parser.parse_string("[logs] {\n    *.log\n}\n[build] {\n    target/\n    *.bin\n}\n").unwrap();
+        let tree = PathTree::from_listing("target/\ntarget/app\ndebug.log\nsmall.bin\t10\nlarge.bin\t2000000\nsrc/main.rs\n").unwrap();
+
+        assert!(flat.equivalent_on(&flat, &tree).is_equivalent());
+
+        let report = flat.equivalent_on(&grouped, &tree);
+        assert_eq!(report.checked, 6);
+        assert_eq!(report.counterexamples.len(), 1);
+        assert_eq!(report.counterexamples[0].to_string(), "small.bin: tracked by no rule, ignored by [build] *.bin");
+    }
+}
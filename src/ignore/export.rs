@@ -0,0 +1,136 @@
+// Module: ignore/export.rs
+//
+// This module writes a `DotIgnore` as a .gitignore or .svnignore file, for
+// tools that only read those. Rules the target format cannot express are left
+// out and listed, rather than written with a different meaning.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+
+use super::{DotIgnore, Pattern};
+
+/// Ignore file formats of other version control systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Git,
+    Svn,
+}
+
+impl ExportFormat {
+    /// File name the format is usually stored in
+    pub fn default_file_name(self) -> &'static str {
+        match self {
+            ExportFormat::Git => ".gitignore",
+            ExportFormat::Svn => ".svnignore",
+        }
+    }
+
+    /// Line for `pattern` in this format, or why it cannot be written
+    fn line(self, pattern: &Pattern) -> Result<String, &'static str> {
+        let glob = pattern.pattern.trim();
+        if pattern.is_size_condition() {
+            return Err("size conditions are not supported");
+        }
+        if pattern.is_preserve() {
+            return Err("directory preservation is not supported");
+        }
+
+        match self {
+            ExportFormat::Git => Ok(pattern.original.clone()),
+            ExportFormat::Svn if pattern.is_negated() => Err("svn:ignore has no negations"),
+            ExportFormat::Svn if glob.trim_end_matches('/').contains('/') => Err("svn:ignore only matches names within a directory"),
+            ExportFormat::Svn => Ok(glob.trim_end_matches('/').to_string()),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "git" | "gitignore" => Ok(ExportFormat::Git),
+            "svn" | "svnignore" => Ok(ExportFormat::Svn),
+            _ => Err(anyhow!("Unknown export format: {} (expected git or svn)", s)),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Git => f.write_str("git"),
+            ExportFormat::Svn => f.write_str("svn"),
+        }
+    }
+}
+
+/// Outcome of `DotIgnore::export`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportResult {
+    /// Text of the exported file
+    pub content: String,
+    /// Number of patterns written
+    pub pattern_count: usize,
+    /// Patterns left out, with the reason
+    pub skipped: Vec<String>,
+}
+
+impl DotIgnore {
+    /// Write the rules in another version control system's ignore format.
+    ///
+    /// Patterns outside groups come first, then each group under a comment
+    /// with its name. Groups for every platform are written, since the file
+    /// is shared; groups scoped to a profile only when the profile is enabled.
+    pub fn export(&self, format: ExportFormat) -> ExportResult {
+        let mut result = ExportResult { content: String::new(), pattern_count: 0, skipped: Vec::new() };
+        let write = |result: &mut ExportResult, pattern: &Pattern| match format.line(pattern) {
+            Ok(line) => {
+                result.content.push_str(&line);
+                result.content.push('\n');
+                result.pattern_count += 1;
+            }
+            Err(reason) => result.skipped.push(format!("{}: {}", pattern.original, reason)),
+        };
+
+        result.content.push_str(&format!("# Exported from .ignore for {}\n", format));
+        for pattern in &self.patterns {
+            write(&mut result, pattern);
+        }
+        for group in self.groups.iter().filter(|group| group.enabled_by(&self.profiles)) {
+            result.content.push_str(&format!("\n# [{}]\n", group.name));
+            for pattern in &group.patterns {
+                write(&mut result, pattern);
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_export() {
+        let content = "#! dotignore 1.3\n*.log\n!keep.log\n[build] {\n    target/\n    docs/api/\n    size:>1MB *.bin\n    &cache/\n}\n[ci] {\n    @profile ci\n    reports/\n}\n";
+        let dotignore = IgnoreParser::new().parse_string(content).unwrap();
+
+        let git = dotignore.export(ExportFormat::Git);
+        assert_eq!(git.content, "# Exported from .ignore for git\n*.log\n!keep.log\n\n# [build]\ntarget/\ndocs/api/\n");
+        assert_eq!(git.pattern_count, 4);
+        assert_eq!(git.skipped, [
+            "size:>1MB *.bin: size conditions are not supported",
+            "&cache/: directory preservation is not supported",
+        ]);
+
+        let svn = dotignore.with_profile(&["ci"]).export(ExportFormat::Svn);
+        assert_eq!(svn.content, "# Exported from .ignore for svn\n*.log\n\n# [build]\ntarget\n\n# [ci]\nreports\n");
+        assert_eq!(svn.skipped.len(), 4);
+    }
+}
@@ -0,0 +1,460 @@
+// Module: ignore/format.rs
+//
+// This module implements the canonical pretty-printer for .DotIgnore files.
+// It works on the lossless syntax tree so comments survive formatting.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use super::names;
+use super::syntax::{GroupNode, IgnoreDocument, SyntaxLine, SyntaxNode};
+
+/// Order in which groups are emitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupOrder {
+    /// Keep groups in file order
+    Preserve,
+    /// Sort groups by name; top-level directives stay in place and split the sort
+    Alphabetical,
+}
+
+impl FromStr for GroupOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "preserve" => Ok(GroupOrder::Preserve),
+            "alphabetical" | "alpha" | "name" => Ok(GroupOrder::Alphabetical),
+            _ => Err(anyhow!("Unknown group order: {}", s)),
+        }
+    }
+}
+
+/// Formatting options for `IgnoreDocument::format`
+#[derive(Debug, Clone)]
+pub struct FormatStyle {
+    /// Number of spaces used to indent group bodies
+    pub indent_width: usize,
+    /// Sort each run of consecutive patterns inside a group; negations are never moved
+    pub sort_patterns: bool,
+    /// Always close groups with `} # end name`; otherwise existing markers are kept
+    pub end_markers: bool,
+    /// Order in which groups are emitted
+    pub group_order: GroupOrder,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            sort_patterns: false,
+            end_markers: false,
+            group_order: GroupOrder::Preserve,
+        }
+    }
+}
+
+/// Add the loose lines of a span, then its groups sorted by name, and empty both
+fn push_sorted_span(blocks: &mut Vec<Vec<String>>, run: &mut Vec<&SyntaxNode>, span: &mut Vec<&GroupChunk>, style: &FormatStyle) {
+    blocks.push(format_loose(run));
+    run.clear();
+    span.sort_by(|a, b| a.group.name.cmp(&b.group.name));
+    for chunk in span.drain(..) {
+        blocks.push(format_chunk(chunk, style));
+    }
+}
+
+/// A group together with the comment lines directly above it
+struct GroupChunk<'a> {
+    comments: Vec<&'a SyntaxLine>,
+    group: &'a GroupNode,
+}
+
+impl IgnoreDocument {
+    /// Pretty-print the document in canonical form.
+    ///
+    /// Fails if the document has syntax errors, since reformatting would hide them.
+    pub fn format(&self, style: &FormatStyle) -> Result<String> {
+        self.to_dotignore()?;
+
+        let newline = self.line_ending().as_str();
+
+        // The version header always stays on the first line, on its own
+        let (header, nodes) = match self.declared_version()? {
+            Some(version) => (Some(version.header()), &self.nodes[1..]),
+            None => (None, &self.nodes[..]),
+        };
+
+        let mut loose: Vec<&SyntaxNode> = Vec::new();
+        let mut chunks: Vec<GroupChunk> = Vec::new();
+        let mut pending_comments: Vec<&SyntaxNode> = Vec::new();
+
+        for node in nodes {
+            match node {
+                SyntaxNode::Comment(_) => pending_comments.push(node),
+                SyntaxNode::Group(group) => {
+                    // Comments that start the file describe it rather than the first group, so sorting leaves them on top
+                    if style.group_order == GroupOrder::Alphabetical && loose.is_empty() && chunks.is_empty() {
+                        loose.append(&mut pending_comments);
+                    }
+                    let comments = pending_comments.drain(..)
+                        .filter_map(|node| match node {
+                            SyntaxNode::Comment(line) => Some(line),
+                            _ => None,
+                        })
+                        .collect();
+                    chunks.push(GroupChunk { comments, group });
+                }
+                _ => {
+                    // Comments separated from the next group by other lines stay where they are
+                    loose.append(&mut pending_comments);
+                    loose.push(node);
+                }
+            }
+        }
+        loose.append(&mut pending_comments);
+
+        let mut blocks: Vec<Vec<String>> = Vec::new();
+
+        match style.group_order {
+            GroupOrder::Preserve => {
+                // Rebuild the original sequence of loose runs and groups
+                let mut loose_iter = loose.into_iter().peekable();
+                let mut run: Vec<&SyntaxNode> = Vec::new();
+                let mut chunk_iter = chunks.iter();
+
+                for node in nodes {
+                    if let SyntaxNode::Group(group) = node {
+                        if !run.is_empty() {
+                            blocks.push(format_loose(&run));
+                            run.clear();
+                        }
+                        let chunk = chunk_iter.next().expect("one chunk per group");
+                        debug_assert!(std::ptr::eq(chunk.group, group));
+                        blocks.push(format_chunk(chunk, style));
+                    } else if loose_iter.peek().is_some_and(|loose| std::ptr::eq(*loose, node)) {
+                        run.push(loose_iter.next().unwrap());
+                    }
+                }
+                if !run.is_empty() {
+                    blocks.push(format_loose(&run));
+                }
+            }
+            GroupOrder::Alphabetical => {
+                // Directives such as `@set` change how the lines below them expand, so they
+                // stay in place and only the groups between two of them are sorted. Within
+                // that span top-level patterns come first; their order relative to groups has no effect
+                let mut loose_iter = loose.into_iter().peekable();
+                let mut run: Vec<&SyntaxNode> = Vec::new();
+                let mut span: Vec<&GroupChunk> = Vec::new();
+                let mut chunk_iter = chunks.iter();
+
+                for node in nodes {
+                    if let SyntaxNode::Group(_) = node {
+                        span.push(chunk_iter.next().expect("one chunk per group"));
+                    } else if loose_iter.peek().is_some_and(|loose| std::ptr::eq(*loose, node)) {
+                        if matches!(node, SyntaxNode::Directive(_)) && !span.is_empty() {
+                            push_sorted_span(&mut blocks, &mut run, &mut span, style);
+                        }
+                        run.push(loose_iter.next().unwrap());
+                    }
+                }
+                push_sorted_span(&mut blocks, &mut run, &mut span, style);
+            }
+        }
+
+        // Blocks are separated by exactly one blank line
+        let lines: Vec<String> = blocks.into_iter()
+            .filter(|block| !block.is_empty())
+            .collect::<Vec<_>>()
+            .join(&String::new());
+
+        let mut output = String::new();
+        if self.bom {
+            output.push('\u{feff}');
+        }
+        if let Some(header) = header {
+            output.push_str(&header);
+            output.push_str(newline);
+        }
+        for line in lines {
+            output.push_str(&line);
+            output.push_str(newline);
+        }
+        Ok(output)
+    }
+}
+
+/// Format top-level lines, collapsing blank lines
+fn format_loose(nodes: &[&SyntaxNode]) -> Vec<String> {
+    let lines = nodes.iter().map(|node| match node {
+        SyntaxNode::Blank(_) => None,
+        SyntaxNode::Comment(line)
+        | SyntaxNode::Directive(line)
+        | SyntaxNode::Pattern(line)
+        | SyntaxNode::UnexpectedEnd(line) => Some(line.trimmed().to_string()),
+        SyntaxNode::Group(_) => unreachable!("groups are formatted as chunks"),
+    });
+    collapse_blank_lines(lines)
+}
+
+/// Format a group with its leading comments
+fn format_chunk(chunk: &GroupChunk, style: &FormatStyle) -> Vec<String> {
+    let indent = " ".repeat(style.indent_width);
+    let group = chunk.group;
+
+    let mut lines: Vec<String> = chunk.comments.iter()
+        .map(|line| line.trimmed().to_string())
+        .collect();
+    lines.push(group.canonical_header());
+
+    let mut body: Vec<Option<String>> = Vec::new();
+    let mut run: Vec<String> = Vec::new();
+    for node in &group.body {
+        match node {
+            SyntaxNode::Pattern(line) => run.push(line.trimmed().to_string()),
+            SyntaxNode::Blank(_) => {
+                flush_run(&mut run, &mut body, style);
+                body.push(None);
+            }
+            SyntaxNode::Comment(line) | SyntaxNode::Directive(line) | SyntaxNode::UnexpectedEnd(line) => {
+                flush_run(&mut run, &mut body, style);
+                body.push(Some(line.trimmed().to_string()));
+            }
+            SyntaxNode::Group(_) => unreachable!("groups do not nest"),
+        }
+    }
+    flush_run(&mut run, &mut body, style);
+
+    for line in collapse_blank_lines(body.into_iter()) {
+        if line.is_empty() {
+            lines.push(line);
+        } else {
+            lines.push(format!("{}{}", indent, line));
+        }
+    }
+
+    if style.end_markers || group.end_marker().is_some() {
+        lines.push(format!("}} # end {}", names::quote(&group.name)));
+    } else {
+        lines.push("}".to_string());
+    }
+    lines
+}
+
+fn flush_run(run: &mut Vec<String>, body: &mut Vec<Option<String>>, style: &FormatStyle) {
+    if style.sort_patterns {
+        // The first matching rule wins, so a negation only applies to the patterns after it:
+        // sort the patterns between negations and leave every negation where it was
+        for chunk in run.chunk_by_mut(|a, b| !a.starts_with('!') && !b.starts_with('!')) {
+            chunk.sort();
+        }
+    }
+    body.extend(run.drain(..).map(Some));
+}
+
+/// Drop leading and trailing blank lines and collapse repeated ones; `None` is a blank line
+fn collapse_blank_lines(lines: impl Iterator<Item = Option<String>>) -> Vec<String> {
+    let mut output: Vec<String> = Vec::new();
+    let mut pending_blank = false;
+
+    for line in lines {
+        match line {
+            None => pending_blank = !output.is_empty(),
+            Some(line) => {
+                if pending_blank {
+                    output.push(String::new());
+                    pending_blank = false;
+                }
+                output.push(line);
+            }
+        }
+    }
+    output
+}
+
+/// Render a unified diff between two texts, line by line
+pub fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new_lines.len() + 1]; old_lines.len() + 1];
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Edit script as (tag, old index, new index)
+    let mut edits = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old_lines.len() || j < new_lines.len() {
+        if i < old_lines.len() && j < new_lines.len() && old_lines[i] == new_lines[j] {
+            edits.push((' ', i, j));
+            i += 1;
+            j += 1;
+        } else if j < new_lines.len() && (i == old_lines.len() || lcs[i][j + 1] > lcs[i + 1][j]) {
+            edits.push(('+', i, j));
+            j += 1;
+        } else {
+            edits.push(('-', i, j));
+            i += 1;
+        }
+    }
+
+    let mut output = String::new();
+    if edits.iter().all(|(tag, _, _)| *tag == ' ') {
+        return output;
+    }
+    output.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+
+    let mut start = 0;
+    while let Some(offset) = edits[start..].iter().position(|(tag, _, _)| *tag != ' ') {
+        let first_change = start + offset;
+        let hunk_start = first_change.saturating_sub(CONTEXT);
+
+        // Extend the hunk while changes are close enough to share context
+        let mut hunk_end = first_change;
+        let mut k = first_change;
+        while k < edits.len() {
+            if edits[k].0 != ' ' {
+                hunk_end = k + 1;
+            } else if k >= hunk_end + 2 * CONTEXT {
+                break;
+            }
+            k += 1;
+        }
+        let hunk_end = (hunk_end + CONTEXT).min(edits.len());
+        let hunk = &edits[hunk_start..hunk_end];
+
+        let old_count = hunk.iter().filter(|(tag, _, _)| *tag != '+').count();
+        let new_count = hunk.iter().filter(|(tag, _, _)| *tag != '-').count();
+        let (_, old_start, new_start) = hunk[0];
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + usize::from(old_count > 0),
+            old_count,
+            new_start + usize::from(new_count > 0),
+            new_count
+        ));
+
+        for &(tag, i, j) in hunk {
+            let line = if tag == '+' { new_lines[j] } else { old_lines[i] };
+            output.push_str(&format!("{}{}\n", tag, line));
+        }
+
+        start = hunk_end;
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_format_default_style() {
+        let input = "\n\n*.tmp   \n\n\n# Build\n[build]   {\n\t*.o\n\n\n  # objects\n      *.obj\n\n}\n[logs] {\n*.log\n} # end logs\n\n\n";
+        let document = IgnoreDocument::parse(input);
+
+        let output = document.format(&FormatStyle::default()).unwrap();
+        assert_eq!(
+            output,
+            "*.tmp\n\n# Build\n[build] {\n    *.o\n\n    # objects\n    *.obj\n}\n\n[logs] {\n    *.log\n} # end logs\n"
+        );
+
+        // Formatting is idempotent
+        let again = IgnoreDocument::parse(&output).format(&FormatStyle::default()).unwrap();
+        assert_eq!(again, output);
+    }
+
+    #[test]
+    fn test_format_custom_style() {
+        let input = "[b] {\n    z\n    !a\n    c\n}\n\n# First group\n[a] {\n    y\n    x\n}\n";
+        let style = FormatStyle {
+            indent_width: 2,
+            sort_patterns: true,
+            end_markers: true,
+            group_order: GroupOrder::Alphabetical,
+        };
+
+        let output = IgnoreDocument::parse(input).format(&style).unwrap();
+        assert_eq!(
+            output,
+            "# First group\n[a] {\n  x\n  y\n} # end a\n\n[b] {\n  z\n  !a\n  c\n} # end b\n"
+        );
+    }
+
+    #[test]
+    fn test_format_alphabetical_keeps_directives_in_place() {
+        let input = "#! dotignore 1.2\n@set DIR = one\n[z] {\n    ${DIR}/z\n}\n[y] {\n    ${DIR}/y\n}\n@set DIR = two\n[a] {\n    ${DIR}/a\n}\n";
+        let style = FormatStyle { group_order: GroupOrder::Alphabetical, ..FormatStyle::default() };
+
+        let output = IgnoreDocument::parse(input).format(&style).unwrap();
+        assert_eq!(
+            output,
+            "#! dotignore 1.2\n@set DIR = one\n\n[y] {\n    ${DIR}/y\n}\n\n[z] {\n    ${DIR}/z\n}\n\n@set DIR = two\n\n[a] {\n    ${DIR}/a\n}\n"
+        );
+
+        // Every group still expands the variable to the value set above it
+        let dotignore = IgnoreDocument::parse(&output).to_dotignore().unwrap();
+        let patterns: Vec<&str> = dotignore.groups.iter().map(|group| group.patterns[0].original.as_str()).collect();
+        assert_eq!(patterns, ["one/y", "one/z", "two/a"]);
+    }
+
+    #[test]
+    fn test_format_sort_keeps_negations_in_place() {
+        let input = "[build] {\n    size:>1MB *.bin\n    !big.bin\n    *.bin\n    b.log\n    a.log\n}\n";
+        let style = FormatStyle { sort_patterns: true, ..FormatStyle::default() };
+
+        let output = IgnoreDocument::parse(input).format(&style).unwrap();
+        assert_eq!(output, "[build] {\n    size:>1MB *.bin\n    !big.bin\n    *.bin\n    a.log\n    b.log\n}\n");
+
+        let before = IgnoreParser::new().parse_string(input).unwrap();
+        let after = IgnoreParser::new().parse_string(&output).unwrap();
+        for (path, size) in [("big.bin", 2_000_000), ("big.bin", 10), ("x.bin", 2_000_000)] {
+            assert_eq!(
+                before.is_ignored_with_size_check(path, size),
+                after.is_ignored_with_size_check(path, size),
+                "{path} ({size} bytes)"
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_alphabetical_keeps_file_comments_on_top() {
+        let style = FormatStyle { group_order: GroupOrder::Alphabetical, ..FormatStyle::default() };
+        for input in [
+            "# Project ignore rules\n[zeta] {\n    a\n}\n[alpha] {\n    b\n}\n",
+            "# Project ignore rules\n\n[zeta] {\n    a\n}\n[alpha] {\n    b\n}\n",
+        ] {
+            let output = IgnoreDocument::parse(input).format(&style).unwrap();
+            assert_eq!(output, "# Project ignore rules\n\n[alpha] {\n    b\n}\n\n[zeta] {\n    a\n}\n");
+        }
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_documents() {
+        let document = IgnoreDocument::parse("*.tmp\n}\n");
+        assert!(document.format(&FormatStyle::default()).is_err());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(unified_diff("a", "b", "x\ny\n", "x\ny\n"), "");
+        assert_eq!(
+            unified_diff("a", "b", "x\ny\nz\n", "x\nY\nz\n"),
+            "--- a\n+++ b\n@@ -1,3 +1,3 @@\n x\n-y\n+Y\n z\n"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+// Module: ignore/impact.rs
+//
+// This module evaluates two rule sets against a real directory tree and lists
+// the files and directories that would switch between tracked and ignored,
+// to catch rule changes that hide more than intended.
+
+use std::path::PathBuf;
+
+use super::tree::PathTree;
+use super::DotIgnore;
+
+/// A file or directory whose status differs between two rule sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImpactEntry {
+    /// Path relative to the scanned root, with `/` separators
+    pub path: PathBuf,
+    /// Whether the path is a directory
+    pub is_dir: bool,
+    /// Whether the new rules ignore the path (the old rules did not)
+    pub ignored: bool,
+    /// Pattern that decided the old status, `None` if nothing matched
+    pub old_rule: Option<String>,
+    /// Pattern that decided the new status, `None` if nothing matched
+    pub new_rule: Option<String>,
+    /// Files that changed status with it: 1 for a file, the files below it that
+    /// flipped the same way for a directory
+    pub files: usize,
+    /// Total size of those files in bytes
+    pub bytes: u64,
+}
+
+/// Outcome of `DotIgnore::impact`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImpactReport {
+    /// Paths whose status changes, in walk order
+    pub entries: Vec<ImpactEntry>,
+    /// Number of files and directories evaluated
+    pub scanned: usize,
+    /// Bytes in files the new rules start ignoring
+    pub newly_ignored_bytes: u64,
+    /// Bytes in files the new rules start tracking
+    pub newly_tracked_bytes: u64,
+}
+
+impl ImpactReport {
+    /// Check if the rule change affects no path in the tree
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl DotIgnore {
+    /// List the paths of `tree` whose status differs between these (old) rules
+    /// and `new`. The tree must list every directory before its contents, as
+    /// `PathTree::scan` does.
+    ///
+    /// Files below a directory that flips the same way are folded into that
+    /// directory's entry.
+    pub fn impact(&self, new: &DotIgnore, tree: &PathTree) -> ImpactReport {
+        let mut report = ImpactReport::default();
+        // Directories that flipped, as (path, index in entries)
+        let mut flipped_dirs: Vec<(PathBuf, usize)> = Vec::new();
+
+        for entry in &tree.entries {
+            let (path, size) = (entry.path.clone(), entry.size);
+            let is_dir = entry.is_dir();
+            report.scanned += 1;
+
+            let old_rule = self.matching_rule_with_size(&path, size);
+            let new_rule = new.matching_rule_with_size(&path, size);
+            let was_ignored = old_rule.is_some_and(|rule| rule.is_ignored());
+            let ignored = new_rule.is_some_and(|rule| rule.is_ignored());
+            if was_ignored == ignored {
+                continue;
+            }
+
+            let bytes = size.unwrap_or(0);
+            if ignored {
+                report.newly_ignored_bytes += bytes;
+            } else {
+                report.newly_tracked_bytes += bytes;
+            }
+
+            // Fold into the nearest flipped directory above when it went the same way
+            flipped_dirs.retain(|(dir, _)| path.starts_with(dir));
+            if let Some(&(_, index)) = flipped_dirs.last() {
+                let parent = &mut report.entries[index];
+                if parent.ignored == ignored {
+                    if !is_dir {
+                        parent.files += 1;
+                        parent.bytes += bytes;
+                    }
+                    continue;
+                }
+            }
+
+            if is_dir {
+                flipped_dirs.push((path.clone(), report.entries.len()));
+            }
+            report.entries.push(ImpactEntry {
+                path,
+                is_dir,
+                ignored,
+                old_rule: old_rule.map(|rule| rule.to_string()),
+                new_rule: new_rule.map(|rule| rule.to_string()),
+                files: usize::from(!is_dir),
+                bytes,
+            });
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_impact() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src/util")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("src/util/mod.rs"), "").unwrap();
+        fs::write(root.join("debug.log"), "0123456789").unwrap();
+        fs::write(root.join(".git/HEAD"), "ref").unwrap();
+
+        let parser = IgnoreParser::new();
+        let old = parser.parse_string("*.log\n").unwrap();
+        let new = parser.parse_string("[all] {\n    src/\n}\n").unwrap();
+
+        let report = old.impact(&new, &PathTree::scan(root).unwrap());
+        assert_eq!(report.scanned, 5);
+        assert_eq!(report.entries.len(), 2);
+
+        let log = &report.entries[0];
+        assert_eq!(log.path, Path::new("debug.log"));
+        assert!(!log.ignored);
+        assert_eq!(log.old_rule.as_deref(), Some("*.log"));
+        assert_eq!(log.new_rule, None);
+
+        let src = &report.entries[1];
+        assert_eq!(src.path, Path::new("src"));
+        assert!(src.is_dir && src.ignored);
+        assert_eq!(src.new_rule.as_deref(), Some("[all] src/"));
+        assert_eq!((src.files, src.bytes), (2, 12));
+
+        assert_eq!(report.newly_ignored_bytes, 12);
+        assert_eq!(report.newly_tracked_bytes, 10);
+        assert!(old.impact(&old, &PathTree::scan(root).unwrap()).is_empty());
+    }
+}
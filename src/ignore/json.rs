@@ -0,0 +1,94 @@
+// Module: ignore/json.rs
+//
+// This module provides JSON export and import of rule sets, available with the
+// `serde` feature. The layout is described by `schema/dotignore.schema.json`.
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::DotIgnore;
+
+/// JSON Schema describing the output of `DotIgnore::to_json`
+pub const JSON_SCHEMA: &str = include_str!("../../schema/dotignore.schema.json");
+
+/// Serialise any part of the data model as pretty-printed JSON
+pub fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string_pretty(value).context("Failed to serialize to JSON")
+}
+
+/// Deserialise any part of the data model from JSON
+pub fn from_json<T: DeserializeOwned>(json: &str) -> Result<T> {
+    serde_json::from_str(json).context("Failed to parse JSON")
+}
+
+impl DotIgnore {
+    /// Serialise the rule set as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        to_json(self)
+    }
+
+    /// Load a rule set from JSON produced by `to_json`; patterns are compiled again
+    pub fn from_json(json: &str) -> Result<Self> {
+        from_json(json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::{ConversionResult, Diagnostic, IgnoreParser, Platform};
+
+    #[test]
+    fn test_round_trip() {
+        let content = "#! dotignore 1.3\n*.tmp\n\n[platform:windows] {\n    Thumbs.db\n}\n\n[build] {\n    @profile ci\n    target/\n    !target/keep\n    size:>10MB *.bin\n}\n";
+        let dotignore = IgnoreParser::new().parse_string(content).unwrap()
+            .for_platform(Platform::Windows)
+            .with_profile(&["ci"]);
+
+        let json = dotignore.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], "1.3");
+        assert_eq!(value["platform"], "windows");
+        assert_eq!(value["patterns"][0], "*.tmp");
+        assert_eq!(value["groups"][1]["profiles"][0], "ci");
+        assert_eq!(value["groups"][1]["patterns"][2], "size:>10MB *.bin");
+        assert!(value.get("diagnostics").is_none());
+
+        let loaded = DotIgnore::from_json(&json).unwrap();
+        assert_eq!(loaded.version(), dotignore.version());
+        assert_eq!(loaded.profiles(), ["ci"]);
+        for path in ["a.tmp", "Thumbs.db", "target/app", "target/keep", "src/main.rs"] {
+            assert_eq!(loaded.is_ignored(path), dotignore.is_ignored(path), "{}", path);
+        }
+        assert!(loaded.groups[1].patterns[2].is_size_condition());
+    }
+
+    #[test]
+    fn test_other_types() {
+        let diagnostic = Diagnostic::warning(Some(3), "Unused variable");
+        let json = to_json(&diagnostic).unwrap();
+        assert!(json.contains("\"severity\": \"warning\""));
+        assert_eq!(from_json::<Diagnostic>(&json).unwrap(), diagnostic);
+
+        let result: ConversionResult = from_json(
+            r#"{"source_file": ".gitignore", "destination_file": ".ignore", "pattern_count": 2, "standard_patterns": 1, "platform_patterns": {"logs": 1}}"#
+        ).unwrap();
+        assert_eq!(result.platform_patterns["logs"], 1);
+
+        // Missing fields fall back to their defaults, bad versions are rejected
+        let dotignore = DotIgnore::from_json(r#"{"patterns": ["*.log"]}"#).unwrap();
+        assert!(dotignore.is_ignored("app.log"));
+        assert!(DotIgnore::from_json(r#"{"version": "one"}"#).is_err());
+    }
+
+    #[test]
+    fn test_schema_is_valid_json() {
+        let schema: serde_json::Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&DotIgnore::new().to_json().unwrap()).unwrap();
+        for key in value.as_object().unwrap().keys() {
+            assert!(properties.contains_key(key), "{} is missing from the schema", key);
+        }
+    }
+}
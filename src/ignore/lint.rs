@@ -0,0 +1,440 @@
+// Module: ignore/lint.rs
+//
+// This module implements a static linter for rule sets. Each rule has a stable
+// code (`DI001`...) and a name, and can be set to allow, warn or deny from an
+// `.ignore-lint.toml` file. Findings are reported as `Diagnostic`s.
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "toml")]
+use std::fs;
+#[cfg(feature = "toml")]
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(feature = "toml")]
+use anyhow::Context;
+use anyhow::{anyhow, Error, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::syntax::{SyntaxNode, WrittenPattern};
+use super::writer::split_inherited;
+use super::{Diagnostic, DotIgnore, IgnoreDocument, Pattern, PatternGroup};
+
+/// File the CLI looks for next to a rule set to configure rule levels
+pub const CONFIG_FILE_NAME: &str = ".ignore-lint.toml";
+
+lazy_static! {
+    // Regex to match a pattern made only of wildcards and separators: *, **, *.*, **/*
+    static ref BROAD_PATTERN_RE: Regex = Regex::new(r"^[*?./]*\*[*?./]*$").unwrap();
+}
+
+/// What to do when a rule finds something
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// Do not report
+    Allow,
+    /// Report as a warning
+    Warn,
+    /// Report as an error
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(anyhow!("Unknown lint level: {} (expected allow, warn or deny)", s)),
+        }
+    }
+}
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    /// The same pattern is listed more than once where both copies apply
+    DuplicatePattern,
+    /// A group has no patterns
+    EmptyGroup,
+    /// A negation re-includes paths that no other pattern ignores
+    UnreachableNegation,
+    /// A pattern only matches paths a broader pattern already ignores
+    ShadowedPattern,
+    /// A size condition on a directory pattern, which never matches
+    SizeOnDirectory,
+    /// `&` on a pattern that is not a directory pattern
+    PreserveNonDirectory,
+    /// A pattern such as `*` that matches every file
+    BroadPattern,
+}
+
+impl LintRule {
+    /// Every rule, in code order
+    pub const ALL: [LintRule; 7] = [
+        LintRule::DuplicatePattern,
+        LintRule::EmptyGroup,
+        LintRule::UnreachableNegation,
+        LintRule::ShadowedPattern,
+        LintRule::SizeOnDirectory,
+        LintRule::PreserveNonDirectory,
+        LintRule::BroadPattern,
+    ];
+
+    /// Stable code of the rule
+    pub fn code(&self) -> &'static str {
+        match self {
+            LintRule::DuplicatePattern => "DI001",
+            LintRule::EmptyGroup => "DI002",
+            LintRule::UnreachableNegation => "DI003",
+            LintRule::ShadowedPattern => "DI004",
+            LintRule::SizeOnDirectory => "DI005",
+            LintRule::PreserveNonDirectory => "DI006",
+            LintRule::BroadPattern => "DI007",
+        }
+    }
+
+    /// Name of the rule, accepted in configuration files like the code
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintRule::DuplicatePattern => "duplicate-pattern",
+            LintRule::EmptyGroup => "empty-group",
+            LintRule::UnreachableNegation => "unreachable-negation",
+            LintRule::ShadowedPattern => "shadowed-pattern",
+            LintRule::SizeOnDirectory => "size-on-directory",
+            LintRule::PreserveNonDirectory => "preserve-non-directory",
+            LintRule::BroadPattern => "broad-pattern",
+        }
+    }
+
+    /// Level used when the configuration does not mention the rule
+    pub fn default_level(&self) -> LintLevel {
+        match self {
+            LintRule::SizeOnDirectory => LintLevel::Deny,
+            _ => LintLevel::Warn,
+        }
+    }
+}
+
+impl FromStr for LintRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintRule::ALL.into_iter()
+            .find(|rule| rule.code().eq_ignore_ascii_case(s) || rule.name() == s)
+            .ok_or_else(|| anyhow!("Unknown lint rule: {}", s))
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.code(), self.name())
+    }
+}
+
+/// Level of each rule
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintConfig {
+    levels: HashMap<LintRule, LintLevel>,
+}
+
+impl LintConfig {
+    /// Level `rule` reports at
+    pub fn level(&self, rule: LintRule) -> LintLevel {
+        self.levels.get(&rule).copied().unwrap_or_else(|| rule.default_level())
+    }
+
+    /// Change the level of `rule`
+    pub fn set(&mut self, rule: LintRule, level: LintLevel) {
+        self.levels.insert(rule, level);
+    }
+
+    /// Read a configuration such as:
+    ///
+    /// ```toml
+    /// [rules]
+    /// duplicate-pattern = "deny"
+    /// DI007 = "allow"
+    /// ```
+    #[cfg(feature = "toml")]
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut document: toml::Table = content.parse()?;
+        let rules = match document.remove("rules") {
+            Some(toml::Value::Table(rules)) => rules,
+            Some(_) => return Err(anyhow!("`rules` must be a table")),
+            None => toml::Table::new(),
+        };
+        if let Some(key) = document.keys().next() {
+            return Err(anyhow!("Unknown key `{}`; rule levels go in the [rules] table", key));
+        }
+
+        let mut config = Self::default();
+        for (key, value) in rules {
+            let rule = key.parse::<LintRule>()?;
+            let level = value.as_str()
+                .ok_or_else(|| anyhow!("The level of `{}` must be a string", key))?
+                .parse::<LintLevel>()
+                .with_context(|| format!("for rule `{}`", key))?;
+            config.set(rule, level);
+        }
+
+        Ok(config)
+    }
+
+    /// Load a configuration file
+    #[cfg(feature = "toml")]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid lint configuration {}", path.display()))
+    }
+}
+
+/// Lint a rule set with the default rule levels.
+///
+/// Rule sets carry no line numbers; use [`run_document`] to get them.
+pub fn run(dotignore: &DotIgnore) -> Vec<Diagnostic> {
+    run_with(dotignore, &LintConfig::default())
+}
+
+/// Lint a rule set with the given rule levels
+pub fn run_with(dotignore: &DotIgnore, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut written: Vec<WrittenPattern> = dotignore.patterns.iter()
+        .map(|pattern| WrittenPattern { group: None, line: 0, pattern: pattern.clone() })
+        .collect();
+    for group in &dotignore.groups {
+        let (_, own) = split_inherited(group, &dotignore.groups);
+        written.extend(own.iter().map(|pattern| WrittenPattern { group: Some(group.name.clone()), line: 0, pattern: pattern.clone() }));
+    }
+
+    Linter { dotignore, written: &written, group_lines: HashMap::new(), config }.run()
+}
+
+/// Lint a parsed document, reporting the line of each finding
+pub fn run_document(document: &IgnoreDocument, config: &LintConfig) -> Result<Vec<Diagnostic>> {
+    let (dotignore, written) = document.lower_with_lines()?;
+
+    let mut group_lines = HashMap::new();
+    let mut line = 1;
+    for node in &document.nodes {
+        if let SyntaxNode::Group(group) = node {
+            group_lines.insert(group.name.clone(), line);
+        }
+        line += node.line_count();
+    }
+
+    Ok(Linter { dotignore: &dotignore, written: &written, group_lines, config }.run())
+}
+
+struct Linter<'a> {
+    dotignore: &'a DotIgnore,
+    /// Patterns as written, with line 0 when lines are unknown
+    written: &'a [WrittenPattern],
+    group_lines: HashMap<String, usize>,
+    config: &'a LintConfig,
+}
+
+impl Linter<'_> {
+    fn run(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut report = |rule: LintRule, line: Option<usize>, message: String| {
+            let diagnostic = match self.config.level(rule) {
+                LintLevel::Allow => return,
+                LintLevel::Warn => Diagnostic::warning(line, message),
+                LintLevel::Deny => Diagnostic::error(line, message),
+            };
+            diagnostics.push(diagnostic.with_code(rule.code()));
+        };
+
+        for group in &self.dotignore.groups {
+            if group.patterns.is_empty() {
+                report(LintRule::EmptyGroup, self.group_lines.get(&group.name).copied(), format!("Group [{}] has no patterns", group.name));
+            }
+        }
+
+        for (index, entry) in self.written.iter().enumerate() {
+            let pattern = &entry.pattern;
+            let text = pattern.original.as_str();
+            let line = Some(entry.line).filter(|&line| line > 0);
+
+            if let Some(earlier) = self.written[..index].iter()
+                .find(|other| other.pattern.original == pattern.original && self.overlaps(&other.group, &entry.group))
+            {
+                report(LintRule::DuplicatePattern, line, format!("Pattern '{}' is already listed {}", text, self.location(earlier)));
+            }
+
+            if pattern.is_negated() && self.is_unreachable(pattern) {
+                report(LintRule::UnreachableNegation, line, format!(
+                    "Negation '{}' can never apply: no other pattern ignores the paths it re-includes", text
+                ));
+            }
+
+            if let Some(broader) = self.shadowing(index) {
+                report(LintRule::ShadowedPattern, line, format!(
+                    "Pattern '{}' only matches paths already ignored by '{}' {}", text, broader.pattern.original, self.location(broader)
+                ));
+            }
+
+            if pattern.is_size_condition() && pattern.is_dir_only() {
+                report(LintRule::SizeOnDirectory, line, format!(
+                    "Size condition on directory pattern '{}' never matches; size conditions only apply to files", text
+                ));
+            }
+
+            if pattern.is_preserve() && !pattern.pattern.trim().ends_with('/') {
+                report(LintRule::PreserveNonDirectory, line, format!(
+                    "'&' preserves directories, but '{}' is not a directory pattern; add a trailing '/'", text
+                ));
+            }
+
+            if !pattern.is_negated() && !pattern.is_size_condition() && BROAD_PATTERN_RE.is_match(pattern.pattern.trim()) {
+                report(LintRule::BroadPattern, line, format!(
+                    "Pattern '{}' matches every file; check that it does not hide source code", text
+                ));
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+        diagnostics
+    }
+
+    fn group(&self, name: &Option<String>) -> Option<&PatternGroup> {
+        let name = name.as_deref()?;
+        self.dotignore.groups.iter().find(|group| group.name == name)
+    }
+
+    /// Check if a scope applies on every platform and profile
+    fn always_applies(&self, scope: &Option<String>) -> bool {
+        self.group(scope).is_none_or(PatternGroup::is_unconditional)
+    }
+
+    /// Check if a pattern in `earlier` applies wherever one in `later` does
+    fn overlaps(&self, earlier: &Option<String>, later: &Option<String>) -> bool {
+        earlier == later || self.always_applies(earlier)
+    }
+
+    fn location(&self, entry: &WrittenPattern) -> String {
+        match (&entry.group, entry.line) {
+            (_, line) if line > 0 => format!("at line {}", line),
+            (Some(group), _) => format!("in [{}]", group),
+            (None, _) => "outside groups".to_string(),
+        }
+    }
+
+    /// Check that no pattern ignores a path the negation re-includes.
+    ///
+    /// Only reported when every pattern can be compared, so a negation next to
+    /// a pattern that cannot be modelled is never flagged.
+    fn is_unreachable(&self, negation: &Pattern) -> bool {
+        self.dotignore.patterns.iter()
+            .chain(self.dotignore.groups.iter().flat_map(|group| &group.patterns))
+            .filter(|pattern| !pattern.is_negated())
+            .all(|pattern| pattern.overlaps(negation) == Some(false))
+    }
+
+    /// Find a broader pattern that already ignores everything the pattern at `index` matches
+    fn shadowing(&self, index: usize) -> Option<&WrittenPattern> {
+        let entry = &self.written[index];
+        let pattern = &entry.pattern;
+        if pattern.is_negated() {
+            return None;
+        }
+
+        self.written.iter().find(|other| {
+            let broader = &other.pattern;
+            broader.original != pattern.original
+                && !broader.is_preserve()
+                && self.overlaps(&other.group, &entry.group)
+                && broader.subsumes(pattern)
+                // Patterns that match each other are equivalent, not broader
+                && !pattern.subsumes(broader)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(content: &str) -> Vec<Diagnostic> {
+        run_document(&IgnoreDocument::parse(content), &LintConfig::default()).unwrap()
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<(&str, Option<usize>)> {
+        diagnostics.iter().map(|d| (d.code.as_deref().unwrap(), d.line)).collect()
+    }
+
+    #[test]
+    fn test_rules() {
+        let content = "\
+*.log
+[logs] {
+    *.log
+    debug.log
+    !*.tmp
+}
+[empty] {
+}
+[build] {
+    size:>1MB target/
+    &notes.txt
+}
+[platform:windows] {
+    Thumbs.db
+}
+[platform:macos] {
+    Thumbs.db
+}
+";
+        let diagnostics = lint(content);
+        assert_eq!(codes(&diagnostics), [
+            ("DI001", Some(3)),
+            ("DI004", Some(4)),
+            ("DI002", Some(7)),
+            ("DI005", Some(10)),
+            ("DI006", Some(11)),
+        ]);
+        assert_eq!(diagnostics[0].to_string(), "line 3: warning[DI001]: Pattern '*.log' is already listed at line 1");
+        assert_eq!(diagnostics[1].message, "Pattern 'debug.log' only matches paths already ignored by '*.log' at line 1");
+        assert_eq!(diagnostics[3].severity, super::super::Severity::Error);
+
+        let diagnostics = lint("[all] {\n    *\n    src/*.rs\n}\n");
+        assert_eq!(codes(&diagnostics), [("DI007", Some(2)), ("DI004", Some(3))]);
+
+        let diagnostics = lint("*.log\n!*.tmp\n");
+        assert_eq!(codes(&diagnostics), [("DI003", Some(2))]);
+        assert_eq!(diagnostics[0].message, "Negation '!*.tmp' can never apply: no other pattern ignores the paths it re-includes");
+
+        // Negations of paths inside an ignored directory, or next to patterns that cannot be compared
+        assert!(lint("docs/\n!*.txt\n").is_empty());
+        assert!(lint("&node_modules/\n!*.tmp\n").is_empty());
+
+        // Without a document the location is the group
+        let dotignore = IgnoreDocument::parse(content).to_dotignore().unwrap();
+        assert_eq!(run(&dotignore)[1].message, "Pattern '*.log' is already listed outside groups");
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn test_config() {
+        let config = LintConfig::parse("# CI settings\n[rules]\nbroad-pattern = \"allow\"\nDI002 = 'deny' # strict\n").unwrap();
+        assert_eq!(config.level(LintRule::BroadPattern), LintLevel::Allow);
+        assert_eq!(config.level(LintRule::EmptyGroup), LintLevel::Deny);
+        assert_eq!(config.level(LintRule::DuplicatePattern), LintLevel::Warn);
+
+        let dotignore = IgnoreDocument::parse("[a] {\n}\n[b] {\n    *\n}\n").to_dotignore().unwrap();
+        let diagnostics = run_with(&dotignore, &config);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, super::super::Severity::Error);
+
+        assert!(LintConfig::parse("[rules]\nno-such-rule = \"warn\"").is_err());
+        assert!(LintConfig::parse("[rules]\nDI001 = \"loud\"").is_err());
+        assert!(LintConfig::parse("[rules]\nDI001 = warn").is_err());
+        assert!(LintConfig::parse("DI001 = \"warn\"").is_err());
+        assert!(LintConfig::parse("[other]\n").is_err());
+    }
+}
@@ -0,0 +1,160 @@
+// Module: ignore/matching.rs
+//
+// This module finds the pattern that decides whether a path is ignored, so
+// that tools can explain a result instead of only reporting it.
+
+use std::fmt;
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::syntax::{IgnoreDocument, WrittenPattern};
+use super::{DotIgnore, Pattern, PatternGroup};
+
+/// The pattern that decided whether a path is ignored
+#[derive(Debug, Clone, Copy)]
+pub struct RuleMatch<'a> {
+    /// Group the pattern belongs to, `None` for patterns outside groups
+    pub group: Option<&'a PatternGroup>,
+    /// The deciding pattern
+    pub pattern: &'a Pattern,
+}
+
+impl RuleMatch<'_> {
+    /// Check if the pattern ignores the path (rather than re-including it)
+    pub fn is_ignored(&self) -> bool {
+        !self.pattern.is_negated()
+    }
+}
+
+impl fmt::Display for RuleMatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.group {
+            Some(group) => write!(f, "[{}] {}", group.name, self.pattern.original),
+            None => f.write_str(&self.pattern.original),
+        }
+    }
+}
+
+impl DotIgnore {
+    /// Find the pattern that decides whether `path` is ignored, if any.
+    ///
+    /// Negations win over other patterns, and patterns in groups are checked
+    /// before patterns outside groups, matching [`DotIgnore::is_ignored`].
+    pub fn matching_rule<P: AsRef<Path>>(&self, path: P) -> Option<RuleMatch<'_>> {
+        let path_str = path.as_ref().to_string_lossy();
+        self.find_rule(|pattern| pattern.matches(&path_str))
+    }
+
+    /// Like [`DotIgnore::matching_rule`], for a file of known size.
+    ///
+    /// Size conditions are checked against `size_in_bytes` instead of the file
+    /// system, so `path` may be relative to any directory; `None` stands for a
+    /// directory, which size conditions never match.
+    pub fn matching_rule_with_size<P: AsRef<Path>>(&self, path: P, size_in_bytes: Option<u64>) -> Option<RuleMatch<'_>> {
+        let path_str = path.as_ref().to_string_lossy();
+        self.find_rule(|pattern| pattern.matches_entry(&path_str, size_in_bytes))
+    }
+
+    /// Every pattern outside groups, then every pattern of the active groups
+    pub(crate) fn rules(&self) -> impl Iterator<Item = RuleMatch<'_>> {
+        let root = self.patterns.iter().map(|pattern| RuleMatch { group: None, pattern });
+        let grouped = self.active_groups()
+            .flat_map(|group| group.patterns.iter().map(move |pattern| RuleMatch { group: Some(group), pattern }));
+        root.chain(grouped)
+    }
+
+    fn find_rule<'a>(&'a self, matches: impl Fn(&Pattern) -> bool) -> Option<RuleMatch<'a>> {
+        let root = || self.patterns.iter().map(|pattern| RuleMatch { group: None, pattern });
+        let grouped = || self.active_groups()
+            .flat_map(|group| group.patterns.iter().map(move |pattern| RuleMatch { group: Some(group), pattern }));
+
+        root().chain(grouped())
+            .find(|rule| rule.pattern.is_negated() && matches(rule.pattern))
+            .or_else(|| grouped().chain(root()).find(|rule| !rule.pattern.is_negated() && matches(rule.pattern)))
+    }
+}
+
+/// Lines on which the patterns of a document are written, to locate the
+/// rules returned by [`DotIgnore::matching_rule`]
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    written: Vec<WrittenPattern>,
+}
+
+impl SourceMap {
+    /// 1-based line of the pattern that `rule` refers to.
+    ///
+    /// Patterns a group inherits or pulls in with `@use` are located where
+    /// they are written in the other group.
+    pub fn line_of(&self, rule: &RuleMatch<'_>) -> Option<usize> {
+        self.line_of_pattern(rule.group.map(|group| group.name.as_str()), &rule.pattern.original)
+    }
+
+    /// 1-based line of `pattern` (as written) in `group`, or in any group if
+    /// the group does not write it
+    pub fn line_of_pattern(&self, group: Option<&str>, pattern: &str) -> Option<usize> {
+        let same_text = |entry: &&WrittenPattern| entry.pattern.original == pattern;
+        self.written.iter()
+            .filter(same_text)
+            .find(|entry| entry.group.as_deref() == group)
+            .or_else(|| self.written.iter().find(same_text))
+            .map(|entry| entry.line)
+    }
+}
+
+impl IgnoreDocument {
+    /// Lower the document like [`IgnoreDocument::to_dotignore`], keeping the
+    /// line of every pattern
+    pub fn to_dotignore_with_source_map(&self) -> Result<(DotIgnore, SourceMap)> {
+        let (dotignore, written) = self.lower_with_lines()?;
+        Ok((dotignore, SourceMap { written }))
+    }
+}
+
+impl Pattern {
+    /// Check if the pattern matches a file of known size, or a directory when
+    /// `size_in_bytes` is `None`
+    pub(crate) fn matches_entry(&self, path: &str, size_in_bytes: Option<u64>) -> bool {
+        match (self.is_size_condition(), size_in_bytes) {
+            (false, _) => self.matches(path),
+            (true, Some(size)) => self.matches_with_size(path, size),
+            (true, None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ignore::{IgnoreDocument, IgnoreParser};
+
+    #[test]
+    fn test_matching_rule() {
+        let content = "*.log\n!keep.log\n[build] {\n    target/\n    size:>1MB *.bin\n}\n";
+        let dotignore = IgnoreParser::new().parse_string(content).unwrap();
+
+        let rule = dotignore.matching_rule("target/app").unwrap();
+        assert_eq!(rule.to_string(), "[build] target/");
+        assert!(rule.is_ignored());
+
+        let rule = dotignore.matching_rule("keep.log").unwrap();
+        assert_eq!(rule.to_string(), "!keep.log");
+        assert!(!rule.is_ignored());
+        assert!(dotignore.matching_rule("src/main.rs").is_none());
+
+        assert!(dotignore.matching_rule_with_size("data.bin", Some(2 << 20)).is_some());
+        assert!(dotignore.matching_rule_with_size("data.bin", Some(10)).is_none());
+        assert!(dotignore.matching_rule_with_size("data.bin", None).is_none());
+    }
+
+    #[test]
+    fn test_source_map() {
+        let content = "#! dotignore 1.3\n*.log\n[base] {\n    tmp/\n}\n[app] extends [base] {\n    !keep.log\n}\n";
+        let (dotignore, source_map) = IgnoreDocument::parse(content).to_dotignore_with_source_map().unwrap();
+
+        let line = |path: &str| source_map.line_of(&dotignore.matching_rule(path).unwrap());
+        assert_eq!(line("debug.log"), Some(2));
+        assert_eq!(line("keep.log"), Some(7));
+        assert_eq!(line("tmp/a"), Some(4));
+    }
+}
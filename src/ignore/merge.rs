@@ -0,0 +1,379 @@
+// Module: ignore/merge.rs
+//
+// This module merges two rule sets, for example a template and an existing
+// repository file: same-named groups are combined, duplicate patterns dropped,
+// and patterns that one input negates and the other includes are reported.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+
+use super::{DotIgnore, Pattern, PatternGroup};
+
+/// How `DotIgnore::merge` settles a conflict between the two inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the first input's side
+    Ours,
+    /// Keep the second input's side
+    Theirs,
+    /// Keep both sides, the second input's pattern after the first's
+    #[default]
+    Both,
+}
+
+impl FromStr for MergePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ours" => Ok(MergePolicy::Ours),
+            "theirs" => Ok(MergePolicy::Theirs),
+            "both" => Ok(MergePolicy::Both),
+            _ => Err(anyhow!("Unknown merge policy: {} (expected ours, theirs or both)", s)),
+        }
+    }
+}
+
+/// What two inputs disagree on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ConflictKind {
+    /// The same path pattern is negated in one input and included in the other
+    Negation,
+    /// A group is scoped to different profiles
+    Profiles,
+    /// A group extends different parents
+    Parent,
+}
+
+/// A contradiction found while merging
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeConflict {
+    /// Group the conflict is in, `None` for patterns outside groups.
+    /// For a negation, the group of the first input's pattern
+    pub group: Option<String>,
+    /// What the inputs disagree on
+    pub kind: ConflictKind,
+    /// The first input's side, as source text
+    pub ours: String,
+    /// The second input's side, as source text
+    pub theirs: String,
+    /// Group of the second input's pattern for a negation, which may differ from
+    /// `group` since a negation re-includes paths whatever group it is in
+    pub theirs_group: Option<String>,
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(group) = &self.group {
+            write!(f, "[{}] ", group)?;
+        }
+        match self.kind {
+            ConflictKind::Negation if self.theirs_group != self.group => match &self.theirs_group {
+                Some(theirs_group) => write!(f, "'{}' contradicts [{}] '{}'", self.ours, theirs_group, self.theirs),
+                None => write!(f, "'{}' contradicts '{}' outside groups", self.ours, self.theirs),
+            },
+            ConflictKind::Negation => write!(f, "'{}' contradicts '{}'", self.ours, self.theirs),
+            ConflictKind::Profiles => write!(f, "profiles '{}' differ from '{}'", self.ours, self.theirs),
+            ConflictKind::Parent => write!(f, "extends '{}' differs from '{}'", self.ours, self.theirs),
+        }
+    }
+}
+
+/// Outcome of `DotIgnore::merge`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MergeReport {
+    /// Contradictions between the inputs, settled by the merge policy
+    pub conflicts: Vec<MergeConflict>,
+    /// Number of patterns dropped because the merged scope already had them
+    pub duplicates: usize,
+    /// Number of groups present in both inputs
+    pub merged_groups: usize,
+}
+
+impl MergeReport {
+    /// Check if the inputs contradicted each other
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+}
+
+impl DotIgnore {
+    /// Merge `other` into a copy of this rule set.
+    ///
+    /// Patterns outside groups and groups with the same name are combined in
+    /// order, first this rule set's then `other`'s, without duplicates. Groups
+    /// only in `other` are appended. The platform and enabled profiles are
+    /// taken from `self`.
+    ///
+    /// A pattern negated in one input and included in the other is a conflict
+    /// whichever groups the two are in, since a negation wins across groups.
+    pub fn merge(&self, other: &DotIgnore, policy: MergePolicy) -> (DotIgnore, MergeReport) {
+        let mut report = MergeReport::default();
+        let mut merged = DotIgnore::new();
+        merged.version = self.version.max(other.version);
+        merged.platform = self.platform;
+        merged.profiles = self.profiles.clone();
+
+        let opposites = find_opposites(self, other);
+        let root = Scope::new(None, policy, &opposites);
+        merged.patterns = merge_patterns(&root, &self.patterns, &other.patterns, &mut report);
+
+        for ours in &self.groups {
+            let scope = Scope::new(Some(&ours.name), policy, &opposites);
+            let group = match other.groups.iter().find(|g| g.name == ours.name) {
+                Some(theirs) => {
+                    report.merged_groups += 1;
+                    merge_group(&scope, ours, theirs, &mut report)
+                }
+                None => PatternGroup { patterns: merge_patterns(&scope, &ours.patterns, &[], &mut report), ..ours.clone() },
+            };
+            merged.groups.push(group);
+        }
+        for theirs in &other.groups {
+            if !self.groups.iter().any(|g| g.name == theirs.name) {
+                let scope = Scope::new(Some(&theirs.name), policy, &opposites);
+                let patterns = merge_patterns(&scope, &[], &theirs.patterns, &mut report);
+                merged.groups.push(PatternGroup { patterns, ..theirs.clone() });
+            }
+        }
+
+        (merged, report)
+    }
+}
+
+/// A pattern of the second input that the first input has with the opposite sign
+struct Opposite<'a> {
+    theirs: &'a Pattern,
+    /// Group of `theirs`, `None` outside groups
+    theirs_group: Option<&'a str>,
+    ours: &'a Pattern,
+    /// Group of `ours`
+    ours_group: Option<&'a str>,
+}
+
+/// Every pattern of a rule set with its group, those outside groups first
+fn scoped_patterns(dotignore: &DotIgnore) -> Vec<(Option<&str>, &Pattern)> {
+    let root = dotignore.patterns.iter().map(|pattern| (None, pattern));
+    let groups = dotignore.groups.iter()
+        .flat_map(|group| group.patterns.iter().map(move |pattern| (Some(group.name.as_str()), pattern)));
+    root.chain(groups).collect()
+}
+
+/// Find the patterns `theirs` negates and `ours` includes, or the reverse, in any groups
+fn find_opposites<'a>(ours: &'a DotIgnore, theirs: &'a DotIgnore) -> Vec<Opposite<'a>> {
+    let ours = scoped_patterns(ours);
+    let theirs = scoped_patterns(theirs);
+
+    theirs.iter()
+        .filter_map(|&(theirs_group, pattern)| {
+            let &(ours_group, opposite) = ours.iter()
+                .find(|(_, p)| p.is_negated() != pattern.is_negated() && path_key(p) == path_key(pattern))?;
+            // Inputs that already list both sides agree with each other
+            if theirs.iter().any(|(_, p)| p.original.trim() == opposite.original.trim()) {
+                return None;
+            }
+            Some(Opposite { theirs: pattern, theirs_group, ours: opposite, ours_group })
+        })
+        .collect()
+}
+
+/// The scope being merged and what is needed to settle its conflicts
+struct Scope<'a> {
+    /// Group being merged, `None` for patterns outside groups
+    group: Option<&'a str>,
+    policy: MergePolicy,
+    opposites: &'a [Opposite<'a>],
+}
+
+impl<'a> Scope<'a> {
+    fn new(group: Option<&'a str>, policy: MergePolicy, opposites: &'a [Opposite<'a>]) -> Self {
+        Scope { group, policy, opposites }
+    }
+
+    /// Check if `pattern` of the first input gives way to a pattern of the second input in another scope
+    fn is_overridden(&self, pattern: &Pattern) -> bool {
+        self.policy == MergePolicy::Theirs && self.opposites.iter()
+            .any(|opposite| std::ptr::eq(opposite.ours, pattern) && opposite.theirs_group != self.group)
+    }
+
+    /// The contradiction `pattern` of the second input takes part in
+    fn opposite_of(&self, pattern: &Pattern) -> Option<&Opposite<'_>> {
+        self.opposites.iter().find(|opposite| std::ptr::eq(opposite.theirs, pattern))
+    }
+}
+
+fn merge_group(scope: &Scope<'_>, ours: &PatternGroup, theirs: &PatternGroup, report: &mut MergeReport) -> PatternGroup {
+    let policy = scope.policy;
+    let name = Some(ours.name.clone());
+    let mut group = PatternGroup::new(ours.name.clone());
+
+    group.parent = ours.parent.clone();
+    if ours.parent != theirs.parent {
+        report.conflicts.push(MergeConflict {
+            group: name.clone(),
+            kind: ConflictKind::Parent,
+            ours: ours.parent.clone().unwrap_or_default(),
+            theirs: theirs.parent.clone().unwrap_or_default(),
+            theirs_group: name.clone(),
+        });
+        if policy == MergePolicy::Theirs {
+            group.parent = theirs.parent.clone();
+        }
+    }
+
+    group.profiles = ours.profiles.clone();
+    if ours.profiles != theirs.profiles {
+        report.conflicts.push(MergeConflict {
+            group: name.clone(),
+            kind: ConflictKind::Profiles,
+            ours: ours.profiles.join(", "),
+            theirs: theirs.profiles.join(", "),
+            theirs_group: name.clone(),
+        });
+        group.profiles = match policy {
+            MergePolicy::Ours => ours.profiles.clone(),
+            MergePolicy::Theirs => theirs.profiles.clone(),
+            // A group without profiles always applies, so keeping both means dropping the scope
+            MergePolicy::Both if ours.profiles.is_empty() || theirs.profiles.is_empty() => Vec::new(),
+            MergePolicy::Both => {
+                let mut profiles = ours.profiles.clone();
+                profiles.extend(theirs.profiles.iter().filter(|p| !ours.profiles.contains(p)).cloned());
+                profiles
+            }
+        };
+    }
+
+    group.patterns = merge_patterns(scope, &ours.patterns, &theirs.patterns, report);
+    group
+}
+
+/// Combine the patterns of one scope, `ours` first
+fn merge_patterns(scope: &Scope<'_>, ours: &[Pattern], theirs: &[Pattern], report: &mut MergeReport) -> Vec<Pattern> {
+    let mut merged: Vec<Pattern> = Vec::new();
+
+    for pattern in ours {
+        if !scope.is_overridden(pattern) {
+            push_unique(&mut merged, pattern, report);
+        }
+    }
+
+    for pattern in theirs {
+        let Some(opposite) = scope.opposite_of(pattern) else {
+            push_unique(&mut merged, pattern, report);
+            continue;
+        };
+
+        report.conflicts.push(MergeConflict {
+            group: opposite.ours_group.map(String::from),
+            kind: ConflictKind::Negation,
+            ours: opposite.ours.original.trim().to_string(),
+            theirs: pattern.original.trim().to_string(),
+            theirs_group: scope.group.map(String::from),
+        });
+        match scope.policy {
+            MergePolicy::Ours => {}
+            MergePolicy::Theirs => {
+                // In the same scope their pattern takes the place of ours; elsewhere ours was left out
+                match merged.iter_mut().find(|p| p.original == opposite.ours.original) {
+                    Some(slot) => *slot = pattern.clone(),
+                    None => push_unique(&mut merged, pattern, report),
+                }
+            }
+            MergePolicy::Both => push_unique(&mut merged, pattern, report),
+        }
+    }
+
+    merged
+}
+
+fn push_unique(merged: &mut Vec<Pattern>, pattern: &Pattern, report: &mut MergeReport) {
+    if merged.iter().any(|p| p.original.trim() == pattern.original.trim()) {
+        report.duplicates += 1;
+    } else {
+        merged.push(pattern.clone());
+    }
+}
+
+/// Source text of a pattern without its negation
+fn path_key(pattern: &Pattern) -> &str {
+    let text = pattern.original.trim();
+    text.strip_prefix('!').unwrap_or(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    fn parse(content: &str) -> DotIgnore {
+        IgnoreParser::new().parse_string(content).unwrap()
+    }
+
+    #[test]
+    fn test_merge_groups_and_duplicates() {
+        let template = parse("*.tmp\n[build] {\n    target/\n    *.o\n}\n[logs] {\n    *.log\n}\n");
+        let repo = parse("*.tmp\n.env\n[build] {\n    *.o\n    dist/\n}\n[editor] {\n    .vscode/\n}\n");
+
+        let (merged, report) = template.merge(&repo, MergePolicy::default());
+        assert!(!report.has_conflicts());
+        assert_eq!(report.duplicates, 2);
+        assert_eq!(report.merged_groups, 1);
+
+        let originals = |patterns: &[Pattern]| patterns.iter().map(|p| p.original.clone()).collect::<Vec<_>>();
+        assert_eq!(originals(&merged.patterns), ["*.tmp", ".env"]);
+        assert_eq!(originals(&merged.groups[0].patterns), ["target/", "*.o", "dist/"]);
+        let names: Vec<_> = merged.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, ["build", "logs", "editor"]);
+    }
+
+    #[test]
+    fn test_merge_contradictions() {
+        let ours = parse("[logs] {\n    *.log\n}\n");
+        let theirs = parse("#! dotignore 1.3\n[logs] {\n    @profile ci\n    !*.log\n}\n");
+
+        let (merged, report) = ours.merge(&theirs, MergePolicy::Both);
+        assert_eq!(report.conflicts.len(), 2);
+        assert_eq!(report.conflicts[0].kind, ConflictKind::Profiles);
+        assert_eq!(report.conflicts[1].to_string(), "[logs] '*.log' contradicts '!*.log'");
+        assert_eq!(merged.groups[0].patterns.len(), 2);
+        assert!(merged.groups[0].profiles.is_empty());
+
+        let (merged, _) = ours.merge(&theirs, MergePolicy::Ours);
+        assert!(merged.is_ignored("app.log"));
+
+        let (merged, _) = ours.merge(&theirs, MergePolicy::Theirs);
+        assert_eq!(merged.groups[0].patterns.len(), 1);
+        assert_eq!(merged.groups[0].profiles, ["ci"]);
+        assert!("sideways".parse::<MergePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_merge_contradictions_across_groups() {
+        let ours = parse("[logs] {\n    *.log\n}\n");
+        let theirs = parse("[misc] {\n    !*.log\n}\n");
+
+        let (merged, report) = ours.merge(&theirs, MergePolicy::Both);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].kind, ConflictKind::Negation);
+        assert_eq!(report.conflicts[0].to_string(), "[logs] '*.log' contradicts [misc] '!*.log'");
+        assert!(!merged.is_ignored("app.log"));
+
+        let (merged, _) = ours.merge(&theirs, MergePolicy::Ours);
+        assert!(merged.is_ignored("app.log"));
+        assert!(merged.groups[1].patterns.is_empty());
+
+        let (merged, _) = ours.merge(&theirs, MergePolicy::Theirs);
+        assert!(merged.groups[0].patterns.is_empty());
+        assert!(!merged.is_ignored("app.log"));
+
+        let theirs = parse("!*.log\n");
+        let (_, report) = ours.merge(&theirs, MergePolicy::Both);
+        assert_eq!(report.conflicts[0].to_string(), "[logs] '*.log' contradicts '!*.log' outside groups");
+    }
+}
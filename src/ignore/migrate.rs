@@ -0,0 +1,158 @@
+// Module: ignore/migrate.rs
+//
+// This module upgrades .DotIgnore files to the current format version while
+// keeping the meaning of every rule they contain.
+
+use anyhow::{anyhow, Result};
+
+use super::syntax::{IgnoreDocument, SyntaxLine, SyntaxNode};
+use super::variables;
+use super::version::{Feature, FormatVersion};
+
+/// Outcome of `IgnoreDocument::migrate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigrationReport {
+    /// Version the file declared before migrating (1.1 for files without a header)
+    pub from: FormatVersion,
+    /// Version the file declares now
+    pub to: FormatVersion,
+    /// Description of every line that was changed
+    pub changes: Vec<String>,
+}
+
+impl MigrationReport {
+    /// Check if the migration changed anything
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl IgnoreDocument {
+    /// Upgrade the document to `FormatVersion::CURRENT`.
+    ///
+    /// Patterns with `${...}` that older versions read literally but newer
+    /// versions expand are escaped so they keep matching the same files.
+    pub fn migrate(&mut self) -> Result<MigrationReport> {
+        let from = self.version()?;
+        let to = FormatVersion::CURRENT;
+        if from > to {
+            return Err(anyhow!("Cannot migrate from format {} down to {}", from, to));
+        }
+        // A file its own version rejects has no meaning to keep
+        self.to_dotignore()?;
+
+        let mut changes = Vec::new();
+        let mut line_number = 1;
+        let has_header = self.declared_version()?.is_some();
+
+        for node in &mut self.nodes {
+            match node {
+                SyntaxNode::Group(group) => {
+                    for child in &mut group.body {
+                        line_number += 1;
+                        escape_node(child, from, line_number, &mut changes);
+                    }
+                    line_number += 1 + usize::from(group.end.is_some());
+                }
+                _ => {
+                    escape_node(node, from, line_number, &mut changes);
+                    line_number += 1;
+                }
+            }
+        }
+
+        if from != to || !has_header {
+            let header = SyntaxLine::new(to.header(), self.line_ending());
+            if has_header {
+                self.nodes[0] = SyntaxNode::Comment(header);
+            } else {
+                self.nodes.insert(0, SyntaxNode::Comment(header));
+            }
+            changes.insert(0, format!("line 1: declared dotignore format {}", to));
+        }
+
+        Ok(MigrationReport { from, to, changes })
+    }
+}
+
+/// Escape a pattern that `from` reads literally but the current version expands.
+///
+/// Directives need no escaping: one its version does not support is an error.
+fn escape_node(node: &mut SyntaxNode, from: FormatVersion, line_number: usize, changes: &mut Vec<String>) {
+    let SyntaxNode::Pattern(line) = node else { return };
+    if from >= Feature::Variables.min_version() || !variables::has_references(&line.text) {
+        return;
+    }
+
+    changes.push(format!("line {}: escaped literal pattern {:?}", line_number, line.trimmed()));
+    line.text = variables::escape(&line.text);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_headerless_file() {
+        let mut document = IgnoreDocument::parse("# Rules\ncache-${X}\n[odd] {\n    @profile dev\n    *.tmp\n}\n");
+        let before = document.to_dotignore().unwrap();
+        assert_eq!(before.version(), FormatVersion::V1_1);
+        assert!(before.is_ignored("cache-${X}"));
+
+        let report = document.migrate().unwrap();
+        assert_eq!(report.from, FormatVersion::V1_1);
+        assert_eq!(report.changes.len(), 2);
+        assert_eq!(
+            document.to_string(),
+            format!("{}\n# Rules\ncache-$${{X}}\n[odd] {{\n    @profile dev\n    *.tmp\n}}\n", FormatVersion::CURRENT.header())
+        );
+
+        // The migrated file means the same thing under the new version
+        let after = document.to_dotignore().unwrap();
+        assert_eq!(after.version(), FormatVersion::CURRENT);
+        assert!(after.is_ignored("cache-${X}"));
+        assert!(!after.is_ignored("a.tmp"));
+        assert!(after.diagnostics().is_empty());
+        assert!(after.with_profile(&["dev"]).is_ignored("a.tmp"));
+
+        // Migrating again is a no-op
+        assert!(document.migrate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_escapes_variable_syntax() {
+        let content = "#! dotignore 1.1\ncost$${X}\n[a] {\n    @profile dev\n    ${HOME}/*.tmp\n    $HOME\n}\n";
+        let mut document = IgnoreDocument::parse(content);
+        let before = document.to_dotignore().unwrap().with_profile(&["dev"]);
+
+        let report = document.migrate().unwrap();
+        assert_eq!(report.from, FormatVersion::V1_1);
+        assert_eq!(report.changes.len(), 3);
+        assert_eq!(
+            document.to_string(),
+            format!("{}\ncost$$$${{X}}\n[a] {{\n    @profile dev\n    $${{HOME}}/*.tmp\n    $HOME\n}}\n", FormatVersion::CURRENT.header())
+        );
+
+        let after = document.to_dotignore().unwrap().with_profile(&["dev"]);
+        assert!(after.diagnostics().is_empty());
+        for path in ["cost$${X}", "${HOME}/a.tmp", "$HOME"] {
+            assert!(before.is_ignored(path), "{}", path);
+            assert!(after.is_ignored(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_files() {
+        let mut document = IgnoreDocument::parse("#! dotignore 1.99\n*.tmp\n");
+        assert!(document.migrate().is_err());
+    }
+
+    #[test]
+    fn test_migrate_rejects_unsupported_directives() {
+        // Without a header the file is format 1.1, which has no @set
+        let mut document = IgnoreDocument::parse("@set X = y\n*.tmp\n");
+        assert!(document.migrate().is_err());
+        assert_eq!(document.to_string(), "@set X = y\n*.tmp\n");
+    }
+}
@@ -6,18 +6,83 @@
 mod parser;
 mod converter;
 mod pattern;
+mod platform;
+mod syntax;
+mod edit;
+mod format;
+mod version;
+mod diagnostic;
+mod migrate;
+mod variables;
+mod compose;
+mod names;
+mod writer;
+mod export;
+mod structured;
+mod merge;
+mod diff;
+mod matching;
+mod tree;
+mod impact;
+mod equiv;
+mod coverage;
+pub mod lint;
+mod subsume;
+mod simplify;
+mod validate;
+mod walk;
+mod clean;
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+mod record;
 
 pub use parser::IgnoreParser;
 pub use converter::{IgnoreConverter, ConversionResult};
 pub use pattern::{Pattern, PatternGroup, IgnoreFile};
+pub use platform::Platform;
+pub use syntax::{IgnoreDocument, SyntaxNode, GroupNode, SyntaxLine, LineEnding};
+pub use format::{FormatStyle, GroupOrder, unified_diff};
+pub use version::{FormatVersion, Feature};
+pub use diagnostic::{Diagnostic, Severity};
+pub use migrate::MigrationReport;
+pub use structured::StructuredFormat;
+pub use export::{ExportFormat, ExportResult};
+pub use merge::{MergePolicy, MergeConflict, ConflictKind, MergeReport};
+pub use diff::{RuleSetDiff, RuleChange};
+pub use matching::{RuleMatch, SourceMap};
+pub use tree::{PathTree, TreeEntry};
+pub use impact::{ImpactEntry, ImpactReport};
+pub use equiv::{Counterexample, EquivalenceReport};
+pub use coverage::{RuleCoverage, GroupCoverage, CoverageReport};
+pub use simplify::{Redundancy, SimplifyReport};
+pub use walk::{Walk, WalkEntry, EntryStatus, DirSummary};
+pub use clean::{CleanFilter, CleanMode, CleanPlan, CleanFailure, CleanReport, parse_size, parse_age};
+pub use validate::{FileValidation, ValidationReport, DEFAULT_FILE_NAMES, find_ignore_files, validate_file, validate_files};
+#[cfg(feature = "serde")]
+pub use json::{to_json, from_json, JSON_SCHEMA};
+#[cfg(feature = "serde")]
+pub use record::{Record, RECORD_VERSION, RECORD_SCHEMA};
 
 use anyhow::Result;
 use std::path::Path;
 
 /// Core functionality for the Dot ignore system
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct DotIgnore {
     pub patterns: Vec<Pattern>,
     pub groups: Vec<PatternGroup>,
+    /// Platform used to decide which `[platform:<os>]` groups apply
+    platform: Option<Platform>,
+    /// Profiles enabled at runtime, see [`DotIgnore::with_profile`]
+    profiles: Vec<String>,
+    /// Format version the rules were parsed with
+    version: FormatVersion,
+    /// Warnings collected while parsing
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl DotIgnore {
@@ -26,9 +91,66 @@ impl DotIgnore {
         Self {
             patterns: Vec::new(),
             groups: Vec::new(),
+            platform: Platform::current(),
+            profiles: Vec::new(),
+            version: FormatVersion::CURRENT,
+            diagnostics: Vec::new(),
         }
     }
 
+    /// Format version the rules were parsed with
+    pub fn version(&self) -> FormatVersion {
+        self.version
+    }
+
+    /// Warnings reported while parsing
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Evaluate platform groups as if running on `platform` instead of the current OS
+    pub fn for_platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Platform whose `[platform:<os>]` groups are applied
+    pub fn platform(&self) -> Option<Platform> {
+        self.platform
+    }
+
+    /// Enable the given profiles, activating the groups scoped to them
+    pub fn with_profile<S: AsRef<str>>(mut self, profiles: &[S]) -> Self {
+        for profile in profiles {
+            self.enable_profile(profile.as_ref());
+        }
+        self
+    }
+
+    /// Enable a single profile
+    pub fn enable_profile(&mut self, profile: &str) {
+        if !self.profiles.iter().any(|p| p == profile) {
+            self.profiles.push(profile.to_string());
+        }
+    }
+
+    /// Disable a previously enabled profile
+    pub fn disable_profile(&mut self, profile: &str) {
+        self.profiles.retain(|p| p != profile);
+    }
+
+    /// Profiles currently enabled
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    /// Get the groups that apply on the selected platform and profiles
+    pub fn active_groups(&self) -> impl Iterator<Item = &PatternGroup> {
+        self.groups.iter().filter(move |group| {
+            group.applies_to(self.platform) && group.enabled_by(&self.profiles)
+        })
+    }
+
     /// Load a .DotIgnore file from the given path
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let parser = IgnoreParser::new();
@@ -37,42 +159,7 @@ impl DotIgnore {
 
     /// Check if a file should be ignored
     pub fn is_ignored<P: AsRef<Path>>(&self, path: P) -> bool {
-        let path_str = path.as_ref().to_string_lossy();
-        
-        // Primero verificar patrones de negación explícita
-        // Un patrón negado con mayor especificidad tiene prioridad
-        for pattern in &self.patterns {
-            if pattern.is_negated() && pattern.matches(&path_str) {
-                return false; // Este patrón niega explícitamente la coincidencia
-            }
-        }
-        
-        // Verificar patrones en todos los grupos
-        for group in &self.groups {
-            for pattern in &group.patterns {
-                if pattern.is_negated() && pattern.matches(&path_str) {
-                    return false; // Este patrón niega explícitamente la coincidencia
-                }
-            }
-        }
-        
-        // Verificar patrones normales en todos los grupos
-        for group in &self.groups {
-            for pattern in &group.patterns {
-                if !pattern.is_negated() && pattern.matches(&path_str) {
-                    return true; // Coincidencia encontrada
-                }
-            }
-        }
-        
-        // Verificar patrones normales en la raíz
-        for pattern in &self.patterns {
-            if !pattern.is_negated() && pattern.matches(&path_str) {
-                return true; // Coincidencia encontrada
-            }
-        }
-        
-        false // No se encontraron coincidencias
+        self.matching_rule(path).is_some_and(|rule| rule.is_ignored())
     }
 
     /// Add a new pattern to the global patterns list
@@ -112,7 +199,7 @@ impl DotIgnore {
         }
         
         // Check group patterns
-        for group in &self.groups {
+        for group in self.active_groups() {
             for pattern in &group.patterns {
                 if pattern.is_dir_only() && !is_dir {
                     continue;
@@ -134,6 +221,12 @@ impl DotIgnore {
         converter.convert_file(source_path.as_ref(), destination)
     }
 
+    /// Convert a file from another format to .DotIgnore text without writing it
+    pub fn convert_to_string<P: AsRef<Path>, Q: AsRef<Path>>(source_path: P, destination_path: Q) -> Result<(String, ConversionResult)> {
+        let converter = IgnoreConverter::new();
+        converter.convert_to_string(source_path.as_ref(), destination_path.as_ref())
+    }
+
     /// Convert all ignore files in a directory
     pub fn convert_directory<P: AsRef<Path>>(directory_path: P, recursive: bool) -> Result<Vec<ConversionResult>> {
         let converter = IgnoreConverter::new();
@@ -149,6 +242,7 @@ impl DotIgnore {
 
     pub fn example_content() -> String {
         let mut content = String::new();
+        content.push_str(&format!("{}\n", FormatVersion::CURRENT.header()));
         content.push_str("# Archivo .ignore de ejemplo generado automáticamente\n");
         content.push_str("# Para más información, visite https://dotignore.dev\n\n");
         
@@ -223,4 +317,38 @@ mod tests {
         assert!(dotignore.is_ignored("temp/file"));
         assert!(!dotignore.is_ignored("file.rs"));
     }
-} 
+
+    #[test]
+    fn test_platform_groups() {
+        let content = r#"
+[platform:windows] {
+    Thumbs.db
+}
+
+[platform:macos] {
+    .DS_Store
+}
+
+[common] {
+    *.log
+}
+"#;
+        
+        let parser = IgnoreParser::new();
+        
+        let windows = parser.parse_string(content).unwrap().for_platform(Platform::Windows);
+        assert!(windows.is_ignored("Thumbs.db"));
+        assert!(!windows.is_ignored(".DS_Store"));
+        assert!(windows.is_ignored("app.log"));
+        
+        let macos = parser.parse_string(content).unwrap().for_platform(Platform::MacOS);
+        assert!(!macos.is_ignored("Thumbs.db"));
+        assert!(macos.is_ignored(".DS_Store"));
+        assert_eq!(macos.active_groups().count(), 2);
+        
+        let linux = parser.parse_string(content).unwrap().for_platform(Platform::Linux);
+        assert!(!linux.is_ignored("Thumbs.db"));
+        assert!(!linux.is_ignored(".DS_Store"));
+        assert!(linux.is_ignored("app.log"));
+    }
+}
@@ -0,0 +1,188 @@
+// Module: ignore/names.rs
+//
+// This module defines the syntax of group names: bare Unicode identifiers such
+// as `[Archivos_temporales]`, quoted names such as `["build output"]`, and the
+// reserved `platform:`, `vcs:` and `profile:` namespaces whose values are checked.
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::parser::PROFILE_NAME_RE;
+use super::Platform;
+
+lazy_static! {
+    // Regex to match a name that can be written without quotes: letters, marks and digits in any script, plus _ : . -
+    static ref BARE_NAME_RE: Regex = Regex::new(r"^[\p{L}\p{M}\p{N}_:.\-]+$").unwrap();
+}
+
+/// Namespaces whose values are validated
+pub const RESERVED_NAMESPACES: &[&str] = &["platform", "vcs", "profile"];
+
+/// Values accepted in the `vcs:` namespace
+pub const VCS_NAMES: &[&str] = &["git", "svn", "hg"];
+
+/// Decode a group name as written between the brackets of a header.
+///
+/// Quoted names may contain any character, with `\"` and `\\` escapes.
+pub fn parse_group_name(raw: &str) -> Result<String> {
+    let raw = raw.trim();
+
+    let name = match raw.strip_prefix('"') {
+        Some(quoted) => {
+            let (name, rest) = unquote(quoted)?;
+            if !rest.is_empty() {
+                return Err(anyhow!("Unexpected text after quoted group name: {:?}", rest));
+            }
+            name
+        }
+        None if BARE_NAME_RE.is_match(raw) => raw.to_string(),
+        None if !raw.is_empty() && !raw.chars().any(char::is_control) => {
+            return Err(anyhow!("Group name '{}' contains spaces or symbols; quote it as [{}]", raw, quote(raw)));
+        }
+        None => return Err(anyhow!("Invalid group name {:?}", raw)),
+    };
+
+    validate_group_name(&name)?;
+    Ok(name)
+}
+
+/// Check a decoded group name, including the values of reserved namespaces
+pub fn validate_group_name(name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("Group name is empty"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(anyhow!("Group name {:?} contains control characters", name));
+    }
+
+    let Some((namespace, rest)) = name.split_once(':') else { return Ok(()) };
+    if !RESERVED_NAMESPACES.contains(&namespace) {
+        return Ok(());
+    }
+
+    // Reserved namespaces allow further nesting such as [platform:windows:ide]
+    let value = rest.split(':').next().unwrap_or(rest);
+    match namespace {
+        "platform" => {
+            value.parse::<Platform>()
+                .map_err(|_| anyhow!("Unknown platform '{}' in group '{}'; expected windows, macos or linux", value, name))?;
+        }
+        "vcs" => {
+            if !VCS_NAMES.contains(&value) {
+                return Err(anyhow!("Unknown version control system '{}' in group '{}'; expected {}", value, name, VCS_NAMES.join(", ")));
+            }
+        }
+        _ => {
+            if !PROFILE_NAME_RE.is_match(value) {
+                return Err(anyhow!("Invalid profile name '{}' in group '{}'", value, name));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a name the way `parse_group_name` reads it: bare when possible, quoted otherwise
+pub fn quote(name: &str) -> String {
+    if BARE_NAME_RE.is_match(name) {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Split the comma or space separated group names of a `@use` line
+pub(crate) fn parse_group_name_list(list: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut rest = list.trim_start_matches(is_separator);
+
+    while !rest.is_empty() {
+        let token_len = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let (_, after) = unquote(quoted)?;
+                rest.len() - after.len()
+            }
+            None => rest.find(is_separator).unwrap_or(rest.len()),
+        };
+
+        names.push(parse_group_name(&rest[..token_len])?);
+        rest = rest[token_len..].trim_start_matches(is_separator);
+    }
+
+    Ok(names)
+}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+/// Read a quoted name whose opening quote was already removed, returning the
+/// name and the text after the closing quote
+fn unquote(quoted: &str) -> Result<(String, &str)> {
+    let mut name = String::new();
+    let mut chars = quoted.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((name, &quoted[index + 1..])),
+            '\\' => match chars.next() {
+                Some((_, escaped)) => name.push(escaped),
+                None => break,
+            },
+            _ => name.push(c),
+        }
+    }
+
+    Err(anyhow!("Unterminated quoted group name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_group_name() {
+        assert_eq!(parse_group_name("Archivos_temporales").unwrap(), "Archivos_temporales");
+        assert_eq!(parse_group_name("キャッシュ").unwrap(), "キャッシュ");
+        assert_eq!(parse_group_name("\"build output\"").unwrap(), "build output");
+        assert_eq!(parse_group_name(r#""say \"hi\" \\ bye""#).unwrap(), r#"say "hi" \ bye"#);
+        assert_eq!(parse_group_name("platform:windows:ide").unwrap(), "platform:windows:ide");
+
+        let error = parse_group_name("Archivos temporales").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Group name 'Archivos temporales' contains spaces or symbols; quote it as [\"Archivos temporales\"]"
+        );
+        assert!(parse_group_name("\"\"").is_err());
+        assert!(parse_group_name("\"open").is_err());
+        assert!(parse_group_name("\"a\" b").is_err());
+    }
+
+    #[test]
+    fn test_reserved_namespaces() {
+        assert!(validate_group_name("platform:macos").is_ok());
+        assert!(validate_group_name("vcs:git").is_ok());
+        assert!(validate_group_name("profile:release").is_ok());
+        assert!(validate_group_name("build:debug").is_ok());
+
+        assert!(validate_group_name("platform:freebsd").is_err());
+        assert!(validate_group_name("vcs:cvs").is_err());
+        assert!(validate_group_name("profile:").is_err());
+        assert!(validate_group_name("profile:two words").is_err());
+    }
+
+    #[test]
+    fn test_quote_round_trip() {
+        for name in ["build", "build output", "a\"b", "ruta\\x", "Ñandú"] {
+            assert_eq!(parse_group_name(&quote(name)).unwrap(), name);
+        }
+        assert_eq!(quote("build output"), "\"build output\"");
+
+        assert_eq!(
+            parse_group_name_list("base, \"build output\"  logs").unwrap(),
+            ["base", "build output", "logs"]
+        );
+        assert!(parse_group_name_list("\"a, b").is_err());
+    }
+}
@@ -3,20 +3,37 @@
 // This module implements the parser for .DotIgnore files.
 
 use std::path::Path;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use anyhow::{Result, Context, anyhow};
 use regex::Regex;
 use lazy_static::lazy_static;
 
-use super::{DotIgnore, Pattern, PatternGroup, IgnoreFile};
+use super::{DotIgnore, IgnoreDocument, StructuredFormat};
+use super::names;
+use super::syntax::{DocumentBuilder, SyntaxLine};
 
 lazy_static! {
-    // Regex to match the start of a group: [group_name] {
-    static ref GROUP_START_RE: Regex = Regex::new(r"^\s*\[([a-zA-Z0-9_:.-]+)\]\s*\{\s*$").unwrap();
+    // Regex to match the start of a group: [group_name] {, ["group name"] { or [child] extends [parent] {
+    // Names are checked separately, so that malformed names are reported instead of read as patterns
+    pub(crate) static ref GROUP_START_RE: Regex = Regex::new(
+        r#"^\s*\[\s*("(?:[^"\\]|\\.)*"|[^\[\]"]+?)\s*\]\s*(?:extends\s*\[\s*("(?:[^"\\]|\\.)*"|[^\[\]"]+?)\s*\]\s*)?\{\s*$"#
+    ).unwrap();
     
     // Regex to match the end of a group: } # end group_name
-    static ref GROUP_END_RE: Regex = Regex::new(r"^\s*\}\s*(?:#\s*end\s+([a-zA-Z0-9_:.-]+))?\s*$").unwrap();
+    pub(crate) static ref GROUP_END_RE: Regex = Regex::new(r"^\s*\}\s*(?:#\s*end\s+(.+?))?\s*$").unwrap();
+    
+    // Regex to match a profile declaration inside a group: @profile dev, ci
+    pub(crate) static ref PROFILE_DIRECTIVE_RE: Regex = Regex::new(r"^\s*@profile\s+(.+?)\s*$").unwrap();
+    
+    // Regex to match a variable definition: @set BUILD_DIR = target
+    pub(crate) static ref SET_DIRECTIVE_RE: Regex = Regex::new(r"^\s*@set\s+(.+?)\s*$").unwrap();
+    
+    // Regex to match a reference to other groups: @use build, logs
+    pub(crate) static ref USE_DIRECTIVE_RE: Regex = Regex::new(r"^\s*@use\s+(.+?)\s*$").unwrap();
+    
+    // Regex to validate a single profile name
+    pub(crate) static ref PROFILE_NAME_RE: Regex = Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap();
 }
 
 /// Parser for .DotIgnore files
@@ -30,160 +47,95 @@ impl IgnoreParser {
         Self {}
     }
     
-    /// Parse a .DotIgnore file and return a DotIgnore instance
+    /// Parse a .DotIgnore file and return a DotIgnore instance.
+    ///
+    /// Files ending in `.toml`, `.yaml` or `.yml` are read as structured rule sets.
     pub fn parse_file(&self, path: &Path) -> Result<DotIgnore> {
         if !path.exists() {
             return Err(anyhow!("File does not exist: {:?}", path));
         }
         
-        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
-        let reader = BufReader::new(file);
-        
-        let mut ignore_file = IgnoreFile::new(path);
-        
-        // State variables for parsing
-        let mut current_group: Option<PatternGroup> = None;
-        let mut line_number = 0;
-        
-        for line in reader.lines() {
-            line_number += 1;
-            let line = line.with_context(|| format!("Error reading line {} from {:?}", line_number, path))?;
-            
-            // Skip empty lines
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            // Skip comment lines (but not comments at end of line)
-            if line.trim_start().starts_with('#') {
-                continue;
-            }
-            
-            // Check for group start
-            if let Some(captures) = GROUP_START_RE.captures(&line) {
-                // If we're already in a group, add it to the file
-                if let Some(group) = current_group.take() {
-                    ignore_file.add_group(group);
-                }
-                
-                // Start a new group
-                let group_name = captures.get(1).unwrap().as_str();
-                current_group = Some(PatternGroup::new(group_name));
-                continue;
-            }
-            
-            // Check for group end
-            if GROUP_END_RE.is_match(&line) {
-                // Add the current group to the file
-                if let Some(group) = current_group.take() {
-                    ignore_file.add_group(group);
-                } else {
-                    return Err(anyhow!("Unexpected group end at line {}", line_number));
-                }
-                continue;
-            }
-            
-            // Parse pattern
-            let pattern = Pattern::new(&line);
-            
-            // Add pattern to current group or directly to file
-            if let Some(group) = &mut current_group {
-                group.add_pattern(pattern);
-            } else {
-                ignore_file.add_pattern(pattern);
-            }
-        }
-        
-        // Add any remaining group
-        if let Some(group) = current_group.take() {
-            ignore_file.add_group(group);
+        if let Some(format) = StructuredFormat::from_path(path) {
+            let content = fs::read_to_string(path).with_context(|| format!("Failed to read file: {:?}", path))?;
+            return format.parse(&content).map_err(|e| anyhow!("{}: {:#}", path.display(), e));
         }
-        
-        // Create DotIgnore from IgnoreFile
-        let mut dotignore = DotIgnore::new();
-        for pattern in ignore_file.patterns() {
-            dotignore.add_pattern(pattern.clone());
-        }
-        
-        for group in ignore_file.groups() {
-            dotignore.add_group(group.clone());
-        }
-        
-        Ok(dotignore)
+
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        self.parse_reader(BufReader::new(file), &path.display().to_string())
     }
     
     /// Parse a string as a .DotIgnore file content
     pub fn parse_string(&self, content: &str) -> Result<DotIgnore> {
-        let mut ignore_file = IgnoreFile::new(Path::new(".DotIgnore"));
-        
-        // State variables for parsing
-        let mut current_group: Option<PatternGroup> = None;
+        self.parse_reader(content.as_bytes(), ".DotIgnore")
+    }
+    
+    /// Parse .DotIgnore content from any buffered reader, such as stdin or an archive entry.
+    ///
+    /// `origin` names the source in error messages.
+    pub fn parse_reader<R: BufRead>(&self, reader: R, origin: &str) -> Result<DotIgnore> {
+        self.read_document(reader, origin)?
+            .to_dotignore()
+            .map_err(|e| anyhow!("{}: {}", origin, e))
+    }
+    
+    /// Read a lossless syntax tree from a buffered reader, one line at a time.
+    ///
+    /// Accepts UTF-8 with or without a byte order mark and with LF or CRLF line
+    /// endings; UTF-16 and otherwise invalid UTF-8 input is rejected.
+    pub fn read_document<R: BufRead>(&self, mut reader: R, origin: &str) -> Result<IgnoreDocument> {
+        let mut builder = DocumentBuilder::new();
+        let mut buffer = Vec::new();
         let mut line_number = 0;
         
-        for line in content.lines() {
-            line_number += 1;
-            
-            // Skip empty lines
-            if line.trim().is_empty() {
-                continue;
-            }
-            
-            // Skip comment lines (but not comments at end of line)
-            if line.trim_start().starts_with('#') {
-                continue;
-            }
-            
-            // Check for group start
-            if let Some(captures) = GROUP_START_RE.captures(line) {
-                // If we're already in a group, add it to the file
-                if let Some(group) = current_group.take() {
-                    ignore_file.add_group(group);
-                }
-                
-                // Start a new group
-                let group_name = captures.get(1).unwrap().as_str();
-                current_group = Some(PatternGroup::new(group_name));
-                continue;
+        loop {
+            buffer.clear();
+            let read = reader.read_until(b'\n', &mut buffer)
+                .with_context(|| format!("{}: Error reading line {}", origin, line_number + 1))?;
+            if read == 0 {
+                break;
             }
+            line_number += 1;
             
-            // Check for group end
-            if GROUP_END_RE.is_match(line) {
-                // Add the current group to the file
-                if let Some(group) = current_group.take() {
-                    ignore_file.add_group(group);
-                } else {
-                    return Err(anyhow!("Unexpected group end at line {}", line_number));
-                }
-                continue;
+            if line_number == 1 && Self::looks_like_utf16(&buffer) {
+                return Err(anyhow!("{}: File is UTF-16 encoded; save it as UTF-8", origin));
             }
             
-            // Parse pattern
-            let pattern = Pattern::new(line);
-            
-            // Add pattern to current group or directly to file
-            if let Some(group) = &mut current_group {
-                group.add_pattern(pattern);
-            } else {
-                ignore_file.add_pattern(pattern);
-            }
-        }
-        
-        // Add any remaining group
-        if let Some(group) = current_group.take() {
-            ignore_file.add_group(group);
+            let line = std::str::from_utf8(&buffer).map_err(|e| {
+                anyhow!("{}: Invalid UTF-8 at line {}, byte {}", origin, line_number, e.valid_up_to() + 1)
+            })?;
+            builder.push_line(SyntaxLine::from_raw(line));
         }
         
-        // Create DotIgnore from IgnoreFile
-        let mut dotignore = DotIgnore::new();
-        for pattern in ignore_file.patterns() {
-            dotignore.add_pattern(pattern.clone());
-        }
-        
-        for group in ignore_file.groups() {
-            dotignore.add_group(group.clone());
-        }
-        
-        Ok(dotignore)
+        Ok(builder.finish())
+    }
+    
+    /// Detect UTF-16 input from its byte order mark or from NUL bytes in the first line
+    fn looks_like_utf16(first_line: &[u8]) -> bool {
+        first_line.starts_with(&[0xFF, 0xFE]) || first_line.starts_with(&[0xFE, 0xFF]) || first_line.contains(&0)
+    }
+    
+    /// Parse a string into a lossless syntax tree that keeps comments and formatting
+    pub fn parse_document(&self, content: &str) -> IgnoreDocument {
+        IgnoreDocument::parse(content)
+    }
+    
+    /// Parse the comma or space separated profile names of a `@profile` line
+    pub(crate) fn parse_profile_list(list: &str, line_number: usize) -> Result<Vec<String>> {
+        list.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                if PROFILE_NAME_RE.is_match(name) {
+                    Ok(name.to_string())
+                } else {
+                    Err(anyhow!("Invalid profile name '{}' at line {}", name, line_number))
+                }
+            })
+            .collect()
+    }
+    
+    /// Parse the comma or space separated group names of a `@use` line
+    pub(crate) fn parse_group_list(list: &str, line_number: usize) -> Result<Vec<String>> {
+        names::parse_group_name_list(list).map_err(|e| anyhow!("{} at line {}", e, line_number))
     }
 }
 
@@ -196,6 +148,7 @@ impl Default for IgnoreParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ignore::{FormatVersion, Platform, Severity};
     use std::io::Write;
     use tempfile::NamedTempFile;
     
@@ -219,7 +172,7 @@ mod tests {
         write!(file, "{}", content).unwrap();
         
         let parser = IgnoreParser::new();
-        let dotignore = parser.parse_file(file.path()).unwrap();
+        let dotignore = parser.parse_file(file.path()).unwrap().for_platform(Platform::Windows);
         
         // Check patterns and groups
         assert_eq!(dotignore.patterns.len(), 0);
@@ -302,4 +255,180 @@ mod tests {
         assert_eq!(unclosed.patterns.len(), 1);
         assert_eq!(unclosed.patterns[0].pattern, "*.obj");
     }
-} 
+
+    #[test]
+    fn test_profile_declarations() {
+        let content = r#"
+[development:local] {
+    @profile dev, ci
+    .env.local
+}
+
+[profile:release] {
+    *.pdb
+}
+
+[common] {
+    *.log
+}
+"#;
+        
+        let parser = IgnoreParser::new();
+        let dotignore = parser.parse_string(content).unwrap();
+        
+        let local = dotignore.groups.iter().find(|g| g.name == "development:local").unwrap();
+        assert_eq!(local.profiles, vec!["dev", "ci"]);
+        assert_eq!(local.patterns.len(), 1);
+        
+        // Without profiles only unscoped groups apply
+        assert!(!dotignore.is_ignored(".env.local"));
+        assert!(!dotignore.is_ignored("app.pdb"));
+        assert!(dotignore.is_ignored("app.log"));
+        
+        let dev = parser.parse_string(content).unwrap().with_profile(&["dev"]);
+        assert!(dev.is_ignored(".env.local"));
+        assert!(!dev.is_ignored("app.pdb"));
+        
+        let release = parser.parse_string(content).unwrap().with_profile(&["release"]);
+        assert!(!release.is_ignored(".env.local"));
+        assert!(release.is_ignored("app.pdb"));
+    }
+    
+    #[test]
+    fn test_profile_outside_group() {
+        let parser = IgnoreParser::new();
+        assert!(parser.parse_string("@profile dev\n*.log\n").is_err());
+        assert!(parser.parse_string("[a] {\n    @profile dev!\n}\n").is_err());
+    }
+    
+    #[test]
+    fn test_version_header() {
+        let parser = IgnoreParser::new();
+        
+        // Without a header, the file is read with the syntax that shipped before headers
+        let dotignore = parser.parse_string("[a] {\n    @profile dev\n}\ncache-${X}\n").unwrap();
+        assert_eq!(dotignore.version(), FormatVersion::UNVERSIONED);
+        assert_eq!(dotignore.groups[0].profiles, ["dev"]);
+        assert!(dotignore.groups[0].patterns.is_empty());
+        assert_eq!(dotignore.patterns.len(), 1);
+        assert_eq!(dotignore.diagnostics().len(), 1);
+        assert_eq!(dotignore.diagnostics()[0].line, Some(4));
+        
+        // A directive the declared version does not have is an error, not a literal pattern
+        assert!(parser.parse_string("#! dotignore 1.0\n[a] {\n    @profile dev\n}\n").is_err());
+        assert!(parser.parse_string("@set X = y\n").is_err());
+        assert!(parser.parse_string("[a] {\n    *.o\n}\n[b] {\n    @use [a]\n}\n").is_err());
+        
+        // Newer minor versions are read on a best-effort basis, newer major versions are rejected
+        let dotignore = parser.parse_string("#! dotignore 1.99\n*.tmp\n").unwrap();
+        assert_eq!(dotignore.diagnostics().len(), 1);
+        assert!(parser.parse_string("#! dotignore 2.0\n*.tmp\n").is_err());
+        assert!(parser.parse_string("#! dotignore latest\n*.tmp\n").is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let content = r#"#! dotignore 1.2
+@set BUILD_DIR = target
+
+[build] {
+    @set CACHE = ${env:DOTIGNORE_TEST_UNSET_CACHE:-.cache}
+    ${BUILD_DIR}/**
+    ${CACHE}/
+}
+
+[other] {
+    ${CACHE}/
+    ${BUILD_DIR}.log
+}
+"#;
+        
+        let parser = IgnoreParser::new();
+        let dotignore = parser.parse_string(content).unwrap();
+        
+        let build = &dotignore.groups[0];
+        assert_eq!(build.patterns[0].pattern, "target/**");
+        assert_eq!(build.patterns[1].pattern, ".cache/");
+        assert!(dotignore.is_ignored("target/debug/app"));
+        assert!(dotignore.is_ignored("target.log"));
+        
+        // Variables set inside a group end with it; the failing line is dropped
+        let other = &dotignore.groups[1];
+        assert_eq!(other.patterns.len(), 1);
+        assert_eq!(dotignore.diagnostics().len(), 1);
+        assert_eq!(dotignore.diagnostics()[0].severity, Severity::Error);
+        assert_eq!(dotignore.diagnostics()[0].line, Some(11));
+        assert_eq!(dotignore.diagnostics()[0].message, "Undefined variable 'CACHE'");
+        
+        // Older files read references literally
+        let dotignore = parser.parse_string("#! dotignore 1.1\n${BUILD_DIR}/\n").unwrap();
+        assert_eq!(dotignore.patterns[0].pattern, "${BUILD_DIR}/");
+        assert_eq!(dotignore.diagnostics()[0].severity, Severity::Warning);
+    }
+    
+    #[test]
+    fn test_group_composition() {
+        let content = r#"#! dotignore 1.3
+[build:release] extends [build] {
+    *.pdb
+}
+
+[build] {
+    target/
+    @use logs
+    *.o
+}
+
+[logs] {
+    *.log
+}
+"#;
+        
+        let parser = IgnoreParser::new();
+        let dotignore = parser.parse_string(content).unwrap();
+        
+        let release = &dotignore.groups[0];
+        assert_eq!(release.parent.as_deref(), Some("build"));
+        let patterns: Vec<&str> = release.patterns.iter().map(|p| p.pattern.as_str()).collect();
+        assert_eq!(patterns, ["target/", "*.log", "*.o", "*.pdb"]);
+        
+        let cycle = "#! dotignore 1.3\n[a] extends [b] {\n}\n[b] {\n    @use a\n}\n";
+        let error = parser.parse_string(cycle).unwrap_err();
+        assert_eq!(error.to_string(), ".DotIgnore: Cycle in group references: a -> b -> a");
+        
+        assert!(parser.parse_string("#! dotignore 1.3\n[a] {\n    @use missing\n}\n").is_err());
+        assert!(parser.parse_string("#! dotignore 1.3\n@use a\n").is_err());
+        assert!(parser.parse_string("#! dotignore 1.2\n[b] {\n}\n[a] extends [b] {\n}\n").is_err());
+    }
+    
+    #[test]
+    fn test_parse_reader_handles_bom_and_crlf() {
+        let content = "\u{feff}*.tmp\r\n[build] {\r\n    *.o\r\n} # end build\r\n";
+        
+        let parser = IgnoreParser::new();
+        let dotignore = parser.parse_reader(content.as_bytes(), "<stdin>").unwrap();
+        
+        assert_eq!(dotignore.patterns.len(), 1);
+        assert_eq!(dotignore.patterns[0].pattern, "*.tmp");
+        assert_eq!(dotignore.groups[0].patterns[0].pattern, "*.o");
+        assert!(dotignore.is_ignored("main.o"));
+        
+        let document = parser.read_document(content.as_bytes(), "<stdin>").unwrap();
+        assert_eq!(document.to_string(), content);
+    }
+    
+    #[test]
+    fn test_parse_reader_rejects_bad_encodings() {
+        let parser = IgnoreParser::new();
+        
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter()
+            .chain("*.tmp\n".encode_utf16().flat_map(|unit| unit.to_le_bytes()))
+            .collect();
+        let error = parser.parse_reader(utf16.as_slice(), "utf16.ignore").unwrap_err();
+        assert_eq!(error.to_string(), "utf16.ignore: File is UTF-16 encoded; save it as UTF-8");
+        
+        let latin1: &[u8] = b"*.tmp\ncaf\xe9\n";
+        let error = parser.parse_reader(latin1, "latin1.ignore").unwrap_err();
+        assert_eq!(error.to_string(), "latin1.ignore: Invalid UTF-8 at line 2, byte 4");
+    }
+}
@@ -7,6 +7,8 @@ use regex::Regex;
 use std::fs;
 use lazy_static::lazy_static;
 
+use super::platform::Platform;
+
 lazy_static! {
     // Regex para detectar condiciones de tamaño: size:<5MB o size:>100KB etc.
     static ref SIZE_CONDITION_RE: Regex = Regex::new(r"^size:([<>])(\d+)([KMG]?B)?\s+(.+)$").unwrap();
@@ -34,6 +36,22 @@ pub struct Pattern {
     pub pattern: String,
 }
 
+/// Patterns are serialised as their source text and compiled again when loaded
+#[cfg(feature = "serde")]
+impl serde::Serialize for Pattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.original)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Pattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let original = String::deserialize(deserializer)?;
+        Ok(Pattern::new(&original))
+    }
+}
+
 /// Comparison type for size conditions
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SizeComparison {
@@ -47,11 +65,17 @@ impl Pattern {
     /// Create a new pattern from a string
     pub fn new(pattern: &str) -> Self {
         let trimmed = pattern.trim();
-        let negated = trimmed.starts_with('!');
+        
+        // A leading backslash makes a `#`, `!` or `@` part of the file name
+        let escaped = trimmed.strip_prefix('\\')
+            .filter(|rest| rest.starts_with(['#', '!', '@']));
+        let negated = escaped.is_none() && trimmed.starts_with('!');
         let dir_only = trimmed.ends_with('/');
         
         // Process the pattern
-        let processed = if negated {
+        let processed = if let Some(rest) = escaped {
+            rest.to_string()
+        } else if negated {
             // Remove the negation character for regex processing
             trimmed[1..].trim().to_string()
         } else {
@@ -131,10 +155,7 @@ impl Pattern {
         
         regex_str.push('$');
         
-        match Regex::new(&regex_str) {
-            Ok(re) => Some(re),
-            Err(_) => None,
-        }
+        Regex::new(&regex_str).ok()
     }
     
     /// Check if this pattern matches the given path
@@ -241,25 +262,50 @@ impl Pattern {
         self.dir_only
     }
     
+    /// Check if this pattern preserves a directory with `&`
+    pub fn is_preserve(&self) -> bool {
+        self.pattern.trim_start().starts_with('&')
+    }
+    
+    /// Check if the glob translated to a regex; a wildcard pattern that did not matches nothing
+    pub(crate) fn has_regex(&self) -> bool {
+        self.regex.is_some()
+    }
+    
     /// Check if this pattern has a size condition
     pub fn is_size_condition(&self) -> bool {
         self.size_condition.is_some()
     }
+
+    /// Size condition as (comparison, size in bytes)
+    pub(crate) fn size_condition(&self) -> Option<(SizeComparison, u64)> {
+        self.size_condition
+    }
 }
 
 /// Represents a pattern group in an ignore file
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PatternGroup {
     /// Name of the group
     pub name: String,
     
     /// Parent group name, if any (for nested groups)
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
     pub parent: Option<String>,
     
     /// Patterns in this group
+    #[cfg_attr(feature = "serde", serde(default))]
     pub patterns: Vec<Pattern>,
+    
+    /// Profiles declared with `@profile`; the group only applies when one is enabled
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub profiles: Vec<String>,
 }
 
+/// Prefix that marks a group as profile-scoped, e.g. `[profile:release]`
+pub const PROFILE_GROUP_PREFIX: &str = "profile:";
+
 impl PatternGroup {
     /// Create a new pattern group with the given name
     pub fn new(name: impl Into<String>) -> Self {
@@ -267,6 +313,7 @@ impl PatternGroup {
             name: name.into(),
             parent: None,
             patterns: Vec::new(),
+            profiles: Vec::new(),
         }
     }
     
@@ -286,6 +333,56 @@ impl PatternGroup {
         self.patterns = patterns;
         self
     }
+    
+    /// Platform this group is scoped to, for `[platform:<os>]` groups
+    pub fn platform(&self) -> Option<Platform> {
+        Platform::from_group_name(&self.name).and_then(|p| p.ok())
+    }
+    
+    /// Check if this group applies on the given platform.
+    ///
+    /// Groups outside the `platform:` namespace always apply; platform groups
+    /// only apply on their own OS, and never when the OS is unknown.
+    pub fn applies_to(&self, platform: Option<Platform>) -> bool {
+        match Platform::from_group_name(&self.name) {
+            None => true,
+            Some(Ok(group_platform)) => platform == Some(group_platform),
+            Some(Err(_)) => false,
+        }
+    }
+    
+    /// Restrict this group to the given profile
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profiles.push(profile.into());
+        self
+    }
+    
+    /// Profiles this group requires, from `@profile` lines and the `profile:` namespace
+    pub fn required_profiles(&self) -> Vec<&str> {
+        let mut profiles: Vec<&str> = self.profiles.iter().map(String::as_str).collect();
+        
+        if let Some(rest) = self.name.strip_prefix(PROFILE_GROUP_PREFIX) {
+            profiles.push(rest.split(':').next().unwrap_or(rest));
+        }
+        
+        profiles
+    }
+    
+    /// Check if this group applies on every platform and with any profiles enabled
+    pub fn is_unconditional(&self) -> bool {
+        Platform::from_group_name(&self.name).is_none() && self.required_profiles().is_empty()
+    }
+
+    /// Check if this group is enabled by the given set of active profiles.
+    ///
+    /// Groups that declare no profile are always enabled; the others need at
+    /// least one of their profiles to be active.
+    pub fn enabled_by<S: AsRef<str>>(&self, active_profiles: &[S]) -> bool {
+        let required = self.required_profiles();
+        
+        required.is_empty() || required.iter()
+            .any(|profile| active_profiles.iter().any(|active| active.as_ref() == *profile))
+    }
 }
 
 /// Represents a complete ignore file
@@ -0,0 +1,93 @@
+// Module: ignore/platform.rs
+//
+// This module defines the operating systems that `[platform:<os>]` groups
+// can be scoped to.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+
+/// Prefix that marks a group as platform-scoped, e.g. `[platform:windows]`
+pub const PLATFORM_GROUP_PREFIX: &str = "platform:";
+
+/// Operating system a platform-scoped group applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Platform {
+    Windows,
+    MacOS,
+    Linux,
+}
+
+impl Platform {
+    /// All supported platforms
+    pub const ALL: [Platform; 3] = [Platform::Windows, Platform::MacOS, Platform::Linux];
+
+    /// The platform this binary was compiled for, if it is a supported one
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "windows") {
+            Some(Platform::Windows)
+        } else if cfg!(target_os = "macos") {
+            Some(Platform::MacOS)
+        } else if cfg!(target_os = "linux") {
+            Some(Platform::Linux)
+        } else {
+            None
+        }
+    }
+
+    /// Canonical name used in group headers
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Windows => "windows",
+            Platform::MacOS => "macos",
+            Platform::Linux => "linux",
+        }
+    }
+
+    /// Extract the platform a group name is scoped to.
+    ///
+    /// Returns `None` for groups outside the `platform:` namespace and
+    /// `Some(Err(_))` for platform groups naming an unknown OS.
+    pub fn from_group_name(group_name: &str) -> Option<Result<Self, Error>> {
+        let rest = group_name.strip_prefix(PLATFORM_GROUP_PREFIX)?;
+        // Allow further nesting such as [platform:windows:ide]
+        let os = rest.split(':').next().unwrap_or(rest);
+        Some(os.parse())
+    }
+}
+
+impl FromStr for Platform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "windows" | "win" => Ok(Platform::Windows),
+            "macos" | "mac" | "osx" | "darwin" => Ok(Platform::MacOS),
+            "linux" => Ok(Platform::Linux),
+            _ => Err(anyhow!("Unknown platform: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_group_name() {
+        assert_eq!(Platform::from_group_name("platform:windows").unwrap().unwrap(), Platform::Windows);
+        assert_eq!(Platform::from_group_name("platform:osx").unwrap().unwrap(), Platform::MacOS);
+        assert_eq!(Platform::from_group_name("platform:linux:desktop").unwrap().unwrap(), Platform::Linux);
+        assert!(Platform::from_group_name("platform:beos").unwrap().is_err());
+        assert!(Platform::from_group_name("build").is_none());
+    }
+}
@@ -0,0 +1,58 @@
+// Module: ignore/record.rs
+//
+// This module defines the envelope of the records the CLI writes with
+// `--output json` and `--output ndjson`, available with the `serde` feature.
+// The record types are described by `schema/cli-output.schema.json`.
+
+use serde::Serialize;
+
+/// Version of the record layout; raised whenever a field is removed or changes meaning
+pub const RECORD_VERSION: u32 = 1;
+
+/// JSON Schema describing the records written by the CLI
+pub const RECORD_SCHEMA: &str = include_str!("../../schema/cli-output.schema.json");
+
+/// One structured output record: a payload tagged with the layout version,
+/// the subcommand that wrote it and the record type
+#[derive(Debug, Clone, Serialize)]
+pub struct Record<'a, T: Serialize> {
+    pub version: u32,
+    pub command: &'a str,
+    #[serde(rename = "type")]
+    pub kind: &'a str,
+    /// Fields of the payload, written next to the envelope fields
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<'a, T: Serialize> Record<'a, T> {
+    /// Create a record with the current layout version
+    pub fn new(command: &'a str, kind: &'a str, data: T) -> Self {
+        Self { version: RECORD_VERSION, command, kind, data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::Diagnostic;
+
+    #[test]
+    fn test_record() {
+        let record = Record::new("validate", "diagnostic", Diagnostic::error(Some(4), "Unclosed group"));
+        let value = serde_json::to_value(&record).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "version": 1,
+            "command": "validate",
+            "type": "diagnostic",
+            "severity": "error",
+            "line": 4,
+            "message": "Unclosed group",
+        }));
+
+        // Every record type is described by the schema
+        let schema: serde_json::Value = serde_json::from_str(RECORD_SCHEMA).unwrap();
+        assert_eq!(schema["properties"]["version"]["const"], RECORD_VERSION);
+        assert!(schema["$defs"]["records"]["diagnostic"].is_object());
+    }
+}
@@ -0,0 +1,183 @@
+// Module: ignore/simplify.rs
+//
+// This module removes patterns that another pattern already covers, such as
+// `debug.log` next to `*.log`, without changing which paths are ignored.
+
+use std::fmt;
+
+use anyhow::Result;
+
+use super::syntax::{IgnoreDocument, WrittenPattern};
+use super::DotIgnore;
+
+/// A pattern line removed because another pattern already covers it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redundancy {
+    /// Removed pattern, as written
+    pub pattern: String,
+    /// Group the removed pattern was in, `None` outside groups
+    pub group: Option<String>,
+    /// 1-based line the removed pattern was on
+    pub line: usize,
+    /// Pattern that covers it, as written
+    pub kept: String,
+    /// Group the covering pattern is in, `None` outside groups
+    pub kept_group: Option<String>,
+    /// 1-based line of the covering pattern, before the removal
+    pub kept_line: usize,
+}
+
+impl fmt::Display for Redundancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: removed '{}'", self.line, self.pattern)?;
+        if let Some(group) = &self.group {
+            write!(f, " from [{}]", group)?;
+        }
+        let reason = if self.pattern == self.kept { "duplicate of" } else { "already matched by" };
+        write!(f, ", {} '{}'", reason, self.kept)?;
+        if let Some(group) = self.kept_group.as_ref().filter(|&group| Some(group) != self.group.as_ref()) {
+            write!(f, " in [{}]", group)?;
+        }
+        write!(f, " (line {})", self.kept_line)
+    }
+}
+
+/// Outcome of `IgnoreDocument::simplify`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimplifyReport {
+    /// Removed patterns, in file order
+    pub removed: Vec<Redundancy>,
+}
+
+impl SimplifyReport {
+    /// Check if nothing was removed
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+impl IgnoreDocument {
+    /// Remove every pattern line that another pattern makes redundant.
+    ///
+    /// A pattern is redundant when a pattern with the same negation subsumes
+    /// it (see [`Pattern::subsumes`](super::Pattern::subsumes)) and applies
+    /// wherever it does: in the same group, outside groups, or in a group
+    /// without platform or profile conditions. Of two equivalent patterns the
+    /// later one is removed. Comments and formatting are kept.
+    pub fn simplify(&mut self) -> Result<SimplifyReport> {
+        let (dotignore, written) = self.lower_with_lines()?;
+        let covers = |outer: &WrittenPattern, inner: &WrittenPattern| {
+            !outer.pattern.is_preserve()
+                && applies_wherever(&dotignore, &outer.group, &inner.group)
+                && outer.pattern.subsumes(&inner.pattern)
+        };
+        // `outer` makes `inner` redundant; equivalent patterns are ordered by line
+        let dominates = |outer: &WrittenPattern, inner: &WrittenPattern| {
+            outer.line != inner.line && covers(outer, inner) && (outer.line < inner.line || !covers(inner, outer))
+        };
+
+        let redundant: Vec<bool> = written.iter()
+            .map(|entry| written.iter().any(|other| dominates(other, entry)))
+            .collect();
+
+        let mut report = SimplifyReport::default();
+        let removed = written.iter().zip(&redundant).filter(|(_, &redundant)| redundant).map(|(entry, _)| entry);
+        for entry in removed {
+            // Some pattern that stays always covers it, since domination is transitive
+            let Some(kept) = written.iter().zip(&redundant)
+                .find(|(other, &redundant)| !redundant && dominates(other, entry))
+                .map(|(other, _)| other)
+            else {
+                continue;
+            };
+            report.removed.push(Redundancy {
+                pattern: entry.pattern.original.clone(),
+                group: entry.group.clone(),
+                line: entry.line,
+                kept: kept.pattern.original.clone(),
+                kept_group: kept.group.clone(),
+                kept_line: kept.line,
+            });
+        }
+
+        let lines: Vec<usize> = report.removed.iter().map(|redundancy| redundancy.line).collect();
+        self.remove_lines(&lines);
+        Ok(report)
+    }
+}
+
+/// Check if patterns in scope `outer` apply wherever patterns in scope `inner` do
+fn applies_wherever(dotignore: &DotIgnore, outer: &Option<String>, inner: &Option<String>) -> bool {
+    outer == inner || outer.as_deref().is_none_or(|name| {
+        dotignore.groups.iter().any(|group| group.name == name && group.is_unconditional())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_simplify() {
+        let content = "\
+#! dotignore 1.3
+*.log
+build/
+# Build output
+[build] {
+    /build/
+    debug.log
+    logs/
+    logs/*
+    logs/
+}
+[platform:windows] {
+    *.tmp
+}
+[cache] {
+    app.tmp
+    !keep.log
+    !keep.log
+}
+";
+        let mut document = IgnoreDocument::parse(content);
+        let report = document.simplify().unwrap();
+
+        let removed: Vec<String> = report.removed.iter().map(ToString::to_string).collect();
+        assert_eq!(removed, [
+            "line 6: removed '/build/' from [build], already matched by 'build/' (line 3)",
+            "line 7: removed 'debug.log' from [build], already matched by '*.log' (line 2)",
+            "line 9: removed 'logs/*' from [build], already matched by 'logs/' (line 8)",
+            "line 10: removed 'logs/' from [build], duplicate of 'logs/' (line 8)",
+            "line 18: removed '!keep.log' from [cache], duplicate of '!keep.log' (line 17)",
+        ]);
+        assert_eq!(document.to_string(), "\
+#! dotignore 1.3
+*.log
+build/
+# Build output
+[build] {
+    logs/
+}
+[platform:windows] {
+    *.tmp
+}
+[cache] {
+    app.tmp
+    !keep.log
+}
+");
+
+        // Same meaning, nothing left to remove
+        assert!(document.simplify().unwrap().is_empty());
+        let parser = IgnoreParser::new();
+        let before = parser.parse_string(content).unwrap();
+        let after = parser.parse_string(&document.to_string()).unwrap();
+        for path in ["a/debug.log", "build/x", "logs/a", "x.tmp", "app.tmp", "keep.log"] {
+            assert_eq!(before.is_ignored(path), after.is_ignored(path), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,423 @@
+// Module: ignore/structured.rs
+//
+// This module maps rule sets to and from structured documents (.ignore.toml and
+// .ignore.yaml) for teams that keep ignore rules beside other configuration.
+// Groups are tables and pattern predicates (negation, size, directory) are fields.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use super::DotIgnore;
+
+/// Syntax a rule set is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuredFormat {
+    /// `.ignore.toml`
+    Toml,
+    /// `.ignore.yaml` or `.ignore.yml`
+    Yaml,
+}
+
+impl StructuredFormat {
+    /// Pick the format from a file extension; `None` means the text syntax
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "toml" => Some(StructuredFormat::Toml),
+            "yaml" | "yml" => Some(StructuredFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Usual file name for a rule set in this format
+    pub fn default_file_name(&self) -> &'static str {
+        match self {
+            StructuredFormat::Toml => ".ignore.toml",
+            StructuredFormat::Yaml => ".ignore.yaml",
+        }
+    }
+
+    /// Read a rule set written in this format
+    pub fn parse(&self, content: &str) -> Result<DotIgnore> {
+        match self {
+            #[cfg(feature = "toml")]
+            StructuredFormat::Toml => model::StructuredFile::from_toml(content)?.into_dotignore(),
+            #[cfg(feature = "yaml")]
+            StructuredFormat::Yaml => model::StructuredFile::from_yaml(content)?.into_dotignore(),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = content;
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    /// Write a rule set in this format
+    pub fn write(&self, dotignore: &DotIgnore) -> Result<String> {
+        match self {
+            #[cfg(feature = "toml")]
+            StructuredFormat::Toml => model::StructuredFile::from_dotignore(dotignore).to_toml(),
+            #[cfg(feature = "yaml")]
+            StructuredFormat::Yaml => model::StructuredFile::from_dotignore(dotignore).to_yaml(),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = dotignore;
+                Err(self.unsupported())
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    fn unsupported(&self) -> Error {
+        anyhow!("{} support requires the `{}` feature", self, self.to_string().to_lowercase())
+    }
+}
+
+impl FromStr for StructuredFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "toml" => Ok(StructuredFormat::Toml),
+            "yaml" | "yml" => Ok(StructuredFormat::Yaml),
+            _ => Err(anyhow!("Unknown structured format: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for StructuredFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructuredFormat::Toml => f.write_str("TOML"),
+            StructuredFormat::Yaml => f.write_str("YAML"),
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl DotIgnore {
+    /// Read a rule set from an .ignore.toml document
+    pub fn from_toml(content: &str) -> Result<Self> {
+        StructuredFormat::Toml.parse(content)
+    }
+
+    /// Write the rule set as an .ignore.toml document
+    pub fn to_toml(&self) -> Result<String> {
+        StructuredFormat::Toml.write(self)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl DotIgnore {
+    /// Read a rule set from an .ignore.yaml document
+    pub fn from_yaml(content: &str) -> Result<Self> {
+        StructuredFormat::Yaml.parse(content)
+    }
+
+    /// Write the rule set as an .ignore.yaml document
+    pub fn to_yaml(&self) -> Result<String> {
+        StructuredFormat::Yaml.write(self)
+    }
+}
+
+#[cfg(any(feature = "toml", feature = "yaml"))]
+mod model {
+    use std::collections::HashMap;
+
+    use anyhow::{anyhow, Context, Result};
+    use lazy_static::lazy_static;
+    use regex::Regex;
+    use serde::{Deserialize, Serialize};
+
+    use crate::ignore::compose::{self, GroupReference};
+    use crate::ignore::names;
+    use crate::ignore::parser::PROFILE_NAME_RE;
+    use crate::ignore::writer::split_inherited;
+    use crate::ignore::{DotIgnore, FormatVersion, Pattern, PatternGroup};
+
+    lazy_static! {
+        // Regex to split the size condition off a pattern: !size:>10MB *.bin
+        static ref SIZE_PREFIX_RE: Regex = Regex::new(r"^!?size:([<>]\d+(?:[KMG]?B)?)\s+(.+)$").unwrap();
+
+        // Regex to validate the `size` field of a rule: >10MB
+        static ref SIZE_RE: Regex = Regex::new(r"^[<>]\d+(?:[KMG]?B)?$").unwrap();
+    }
+
+    /// Top-level document
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub(super) struct StructuredFile {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<FormatVersion>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        patterns: Vec<StructuredPattern>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        groups: Vec<StructuredGroup>,
+    }
+
+    /// A group, written as a table
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct StructuredGroup {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        extends: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        profiles: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        patterns: Vec<StructuredPattern>,
+    }
+
+    /// A pattern, either as source text or as a table of predicates
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum StructuredPattern {
+        Text(String),
+        Rule(PatternRule),
+    }
+
+    /// A pattern with its predicates as separate fields
+    #[derive(Debug, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PatternRule {
+        /// Glob the rule matches, without `!` or size prefix
+        path: String,
+        /// Re-include files matched by other patterns
+        #[serde(default, skip_serializing_if = "is_false")]
+        negate: bool,
+        /// Only match directories, like a trailing `/`
+        #[serde(default, skip_serializing_if = "is_false")]
+        directory: bool,
+        /// Size condition such as `>10MB` or `<500KB`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        size: Option<String>,
+    }
+
+    fn is_false(value: &bool) -> bool {
+        !*value
+    }
+
+    impl StructuredPattern {
+        fn from_pattern(pattern: &Pattern) -> Self {
+            if !pattern.is_negated() && !pattern.is_size_condition() {
+                return StructuredPattern::Text(pattern.original.clone());
+            }
+
+            let (size, path) = match SIZE_PREFIX_RE.captures(&pattern.original) {
+                Some(captures) => (Some(captures[1].to_string()), captures[2].to_string()),
+                None => (None, pattern.pattern.clone()),
+            };
+            StructuredPattern::Rule(PatternRule {
+                path,
+                negate: pattern.is_negated(),
+                directory: false,
+                size,
+            })
+        }
+
+        /// Source text of the pattern as it would appear in a .DotIgnore file
+        fn to_source(&self) -> Result<String> {
+            let rule = match self {
+                StructuredPattern::Text(text) => return Ok(text.clone()),
+                StructuredPattern::Rule(rule) => rule,
+            };
+
+            let path = rule.path.trim();
+            if path.is_empty() {
+                return Err(anyhow!("Pattern rule has an empty path"));
+            }
+
+            let mut source = String::new();
+            if rule.negate {
+                source.push('!');
+            }
+            if let Some(size) = &rule.size {
+                if !SIZE_RE.is_match(size) {
+                    return Err(anyhow!("Invalid size condition '{}' for pattern '{}'", size, path));
+                }
+                source.push_str(&format!("size:{} ", size));
+            } else if !rule.negate && path.starts_with(['#', '!', '@']) {
+                source.push('\\');
+            }
+            source.push_str(path);
+            if rule.directory && !path.ends_with('/') {
+                source.push('/');
+            }
+            Ok(source)
+        }
+    }
+
+    impl StructuredFile {
+        pub(super) fn from_dotignore(dotignore: &DotIgnore) -> Self {
+            let groups = dotignore.groups.iter()
+                .map(|group| {
+                    let (parent, patterns) = split_inherited(group, &dotignore.groups);
+                    StructuredGroup {
+                        name: group.name.clone(),
+                        extends: parent.map(str::to_string),
+                        profiles: group.profiles.clone(),
+                        patterns: patterns.iter().map(StructuredPattern::from_pattern).collect(),
+                    }
+                })
+                .collect();
+
+            Self {
+                version: Some(dotignore.version()),
+                patterns: dotignore.patterns.iter().map(StructuredPattern::from_pattern).collect(),
+                groups,
+            }
+        }
+
+        pub(super) fn into_dotignore(self) -> Result<DotIgnore> {
+            let mut dotignore = DotIgnore::new();
+            if let Some(version) = self.version {
+                if version.major > FormatVersion::CURRENT.major {
+                    return Err(anyhow!(
+                        "File requires dotignore format {}, but this build only supports up to {}",
+                        version, FormatVersion::CURRENT
+                    ));
+                }
+                dotignore.version = version;
+            }
+
+            for pattern in &self.patterns {
+                dotignore.add_pattern(Pattern::new(&pattern.to_source()?));
+            }
+
+            let mut seen = HashMap::new();
+            let mut references = Vec::new();
+            for (index, structured) in self.groups.iter().enumerate() {
+                names::validate_group_name(&structured.name)
+                    .with_context(|| format!("Invalid group #{}", index + 1))?;
+                if seen.insert(structured.name.as_str(), index).is_some() {
+                    return Err(anyhow!("Group '{}' is defined more than once", structured.name));
+                }
+
+                let mut group = PatternGroup::new(structured.name.clone());
+                for profile in &structured.profiles {
+                    if !PROFILE_NAME_RE.is_match(profile) {
+                        return Err(anyhow!("Invalid profile name '{}' in group '{}'", profile, structured.name));
+                    }
+                    group.profiles.push(profile.clone());
+                }
+                for pattern in &structured.patterns {
+                    group.add_pattern(Pattern::new(&pattern.to_source()?));
+                }
+                if let Some(parent) = &structured.extends {
+                    group.parent = Some(parent.clone());
+                    references.push(GroupReference::new(index, 0, parent, 0));
+                }
+                dotignore.add_group(group);
+            }
+
+            for group in &dotignore.groups {
+                if let Some(parent) = group.parent.as_deref().filter(|parent| !seen.contains_key(parent)) {
+                    return Err(anyhow!("Group '{}' extends unknown group '{}'", group.name, parent));
+                }
+            }
+            compose::resolve(&mut dotignore.groups, &references)?;
+
+            Ok(dotignore)
+        }
+
+        #[cfg(feature = "toml")]
+        pub(super) fn from_toml(content: &str) -> Result<Self> {
+            toml::from_str(content).context("Invalid .ignore.toml document")
+        }
+
+        #[cfg(feature = "toml")]
+        pub(super) fn to_toml(&self) -> Result<String> {
+            toml::to_string(self).context("Failed to write TOML")
+        }
+
+        #[cfg(feature = "yaml")]
+        pub(super) fn from_yaml(content: &str) -> Result<Self> {
+            serde_yaml::from_str(content).context("Invalid .ignore.yaml document")
+        }
+
+        #[cfg(feature = "yaml")]
+        pub(super) fn to_yaml(&self) -> Result<String> {
+            serde_yaml::to_string(self).context("Failed to write YAML")
+        }
+    }
+}
+
+#[cfg(all(test, feature = "toml", feature = "yaml"))]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    const CONTENT: &str = "#! dotignore 1.3\n*.tmp\n\\@notes\n\n[base] {\n    target/\n    !target/keep\n}\n\n[\"build output\"] extends [base] {\n    @profile ci\n    size:>10MB *.bin\n    !size:<1KB *.bin\n}\n";
+
+    fn assert_same_rules(a: &DotIgnore, b: &DotIgnore) {
+        assert_eq!(a.version(), b.version());
+        let texts = |patterns: &[crate::ignore::Pattern]| patterns.iter().map(|p| p.original.clone()).collect::<Vec<_>>();
+        assert_eq!(texts(&a.patterns), texts(&b.patterns));
+        assert_eq!(a.groups.len(), b.groups.len());
+        for (x, y) in a.groups.iter().zip(&b.groups) {
+            assert_eq!(x.name, y.name);
+            assert_eq!(x.parent, y.parent);
+            assert_eq!(x.profiles, y.profiles);
+            assert_eq!(texts(&x.patterns), texts(&y.patterns));
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = IgnoreParser::new().parse_string(CONTENT).unwrap();
+
+        let toml = original.to_toml().unwrap();
+        assert!(toml.contains("[[groups]]"), "{}", toml);
+        assert!(toml.contains("extends = \"base\""), "{}", toml);
+        assert_same_rules(&original, &DotIgnore::from_toml(&toml).unwrap());
+
+        let yaml = original.to_yaml().unwrap();
+        assert!(yaml.contains("negate: true"), "{}", yaml);
+        assert_same_rules(&original, &DotIgnore::from_yaml(&yaml).unwrap());
+
+        let text = DotIgnore::from_yaml(&yaml).unwrap().to_text();
+        assert_same_rules(&original, &IgnoreParser::new().parse_string(&text).unwrap());
+    }
+
+    #[test]
+    fn test_hand_written_documents() {
+        let toml = r#"
+patterns = ["*.log"]
+
+[[groups]]
+name = "build"
+patterns = ["target/", { path = "out", directory = true }, { path = "keep.o", negate = true }]
+
+[[groups]]
+name = "release"
+extends = "build"
+profiles = ["release"]
+patterns = [{ path = "*.iso", size = ">1GB" }]
+"#;
+        let dotignore = DotIgnore::from_toml(toml).unwrap().with_profile(&["release"]);
+        assert!(dotignore.is_ignored("out/app"));
+        assert!(!dotignore.is_ignored("keep.o"));
+        assert_eq!(dotignore.groups[1].patterns.len(), 4);
+        assert_eq!(dotignore.groups[1].patterns[3].original, "size:>1GB *.iso");
+
+        let yaml = "groups:\n  - name: a\n    extends: missing\n";
+        assert!(DotIgnore::from_yaml(yaml).is_err());
+        assert!(DotIgnore::from_yaml("groups:\n  - name: \"platform:beos\"\n").is_err());
+        assert!(DotIgnore::from_yaml("groups:\n  - name: a\n  - name: a\n").is_err());
+        assert!(DotIgnore::from_yaml("patterns:\n  - path: x\n    size: huge\n").is_err());
+        assert!(DotIgnore::from_toml("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_parser_picks_format_from_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".ignore.yml");
+        std::fs::write(&path, "patterns:\n  - \"*.log\"\n").unwrap();
+
+        let dotignore = IgnoreParser::new().parse_file(&path).unwrap();
+        assert!(dotignore.is_ignored("app.log"));
+        assert_eq!(StructuredFormat::from_path(Path::new(".ignore")), None);
+    }
+}
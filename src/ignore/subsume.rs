@@ -0,0 +1,328 @@
+// Module: ignore/subsume.rs
+//
+// This module decides when one pattern subsumes another, that is when every
+// path the second matches is also matched by the first. Each pattern is turned
+// into a small automaton over path strings that mirrors `Pattern::matches`,
+// and inclusion (or overlap) is checked on the product of the two determinised
+// automata.
+// Paths are assumed to be normalised: `/` separated, without a trailing `/`.
+
+use std::collections::{BTreeSet, HashSet, VecDeque};
+
+use super::pattern::SizeComparison;
+use super::Pattern;
+
+/// One position of a glob
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A literal character
+    Char(char),
+    /// `?`: any single character
+    Any,
+    /// `*`: any string, including `/`
+    Star,
+    /// `[...]`: any character in one of the ranges
+    Class(Vec<(char, char)>),
+}
+
+impl Token {
+    /// Check if the token repeats (and so may also match nothing)
+    fn is_loop(&self) -> bool {
+        matches!(self, Token::Star)
+    }
+
+    fn accepts(&self, c: char) -> bool {
+        match self {
+            Token::Char(x) => *x == c,
+            Token::Any | Token::Star => true,
+            Token::Class(ranges) => ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi),
+        }
+    }
+}
+
+/// Set of paths a pattern matches, as a union of token sequences
+struct Language {
+    sequences: Vec<Vec<Token>>,
+}
+
+/// Set of (sequence, position) pairs the automaton can be in
+type State = BTreeSet<(usize, usize)>;
+
+impl Language {
+    /// Build the language of a pattern's glob, ignoring negation and size.
+    ///
+    /// Returns `None` for patterns whose matching cannot be modelled exactly.
+    fn of(pattern: &Pattern) -> Option<Self> {
+        let glob = pattern.pattern.trim();
+        if pattern.is_preserve() || !pattern.has_regex() {
+            return None;
+        }
+
+        let literal = |text: &str| text.chars().map(Token::Char).collect::<Vec<_>>();
+        let join = |parts: &[&[Token]]| parts.concat();
+        let slash = [Token::Char('/')];
+        let star = [Token::Star];
+
+        let sequences = if pattern.is_dir_only() {
+            // The directory itself, anything below it, or any path component with its name
+            let name = glob.trim_end_matches('/');
+            if name.is_empty() || pattern.is_size_condition() {
+                return None;
+            }
+            let name = literal(name);
+            let mut sequences = vec![name.clone(), join(&[&name, &slash, &star])];
+            if !glob.trim_end_matches('/').contains('/') {
+                sequences.push(join(&[&star, &slash, &name]));
+                sequences.push(join(&[&star, &slash, &name, &slash, &star]));
+            }
+            sequences
+        } else if !glob.contains(['*', '?']) {
+            // Compared with the file name of the path
+            if glob.contains('/') {
+                return None;
+            }
+            let name = literal(glob);
+            vec![name.clone(), join(&[&star, &slash, &name])]
+        } else {
+            // A regex over the whole path
+            vec![Self::wildcards(glob.trim_end_matches('/'))?]
+        };
+
+        Some(Self { sequences })
+    }
+
+    /// Tokenise a glob the way `Pattern::pattern_to_regex` translates it
+    fn wildcards(glob: &str) -> Option<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut chars = glob.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => tokens.push(Token::Star),
+                '?' => tokens.push(Token::Any),
+                '[' => {
+                    let mut class = Vec::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => class.push(c),
+                            // An unclosed class does not compile, so the pattern matches nothing
+                            None => return None,
+                        }
+                    }
+                    // Only plain sets and ranges mean the same in the glob and the regex
+                    if class.is_empty() || class.iter().any(|c| "*?.\\$^+(){}|[!".contains(*c)) {
+                        return None;
+                    }
+                    let mut ranges = Vec::new();
+                    let mut i = 0;
+                    while i < class.len() {
+                        if i + 2 < class.len() && class[i + 1] == '-' {
+                            if class[i] > class[i + 2] {
+                                return None;
+                            }
+                            ranges.push((class[i], class[i + 2]));
+                            i += 3;
+                        } else {
+                            ranges.push((class[i], class[i]));
+                            i += 1;
+                        }
+                    }
+                    tokens.push(Token::Class(ranges));
+                }
+                ']' => return None,
+                _ => tokens.push(Token::Char(c)),
+            }
+        }
+
+        Some(tokens)
+    }
+
+    /// Add the positions reachable by skipping repeated tokens
+    fn close(&self, mut state: State) -> State {
+        let mut pending: Vec<_> = state.iter().copied().collect();
+        while let Some((sequence, position)) = pending.pop() {
+            if self.sequences[sequence].get(position).is_some_and(Token::is_loop) && state.insert((sequence, position + 1)) {
+                pending.push((sequence, position + 1));
+            }
+        }
+        state
+    }
+
+    fn start(&self) -> State {
+        self.close((0..self.sequences.len()).map(|sequence| (sequence, 0)).collect())
+    }
+
+    fn step(&self, state: &State, c: char) -> State {
+        let next = state.iter()
+            .filter_map(|&(sequence, position)| {
+                let token = self.sequences[sequence].get(position)?;
+                match token.accepts(c) {
+                    true if token.is_loop() => Some((sequence, position)),
+                    true => Some((sequence, position + 1)),
+                    false => None,
+                }
+            })
+            .collect();
+        self.close(next)
+    }
+
+    fn accepts(&self, state: &State) -> bool {
+        state.iter().any(|&(sequence, position)| position == self.sequences[sequence].len())
+    }
+
+    /// One character from every range of characters the tokens treat alike
+    fn alphabet(&self, other: &Language) -> Vec<char> {
+        let mut bounds: BTreeSet<u32> = [0, '/' as u32, '/' as u32 + 1, '\n' as u32, '\n' as u32 + 1].into();
+        for token in self.sequences.iter().chain(&other.sequences).flatten() {
+            match token {
+                Token::Char(c) => bounds.extend([*c as u32, *c as u32 + 1]),
+                Token::Class(ranges) => bounds.extend(ranges.iter().flat_map(|&(lo, hi)| [lo as u32, hi as u32 + 1])),
+                _ => {}
+            }
+        }
+        // Paths never contain newlines, which `.` in the regex would not match
+        bounds.into_iter().filter(|&b| b != '\n' as u32).filter_map(char::from_u32).collect()
+    }
+
+    /// Check if every path in `inner` is also in `self`.
+    ///
+    /// Paths are taken to be normalised, so paths ending in `/` are not counted.
+    fn includes(&self, inner: &Language) -> bool {
+        let alphabet = self.alphabet(inner);
+        let start = (inner.start(), self.start(), false);
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some((inner_state, outer_state, trailing_slash)) = queue.pop_front() {
+            if !trailing_slash && inner.accepts(&inner_state) && !self.accepts(&outer_state) {
+                return false;
+            }
+            for &c in &alphabet {
+                let next = (inner.step(&inner_state, c), self.step(&outer_state, c), c == '/');
+                if !next.0.is_empty() && seen.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Check if some path is in both `self` and `other`
+    fn intersects(&self, other: &Language) -> bool {
+        let alphabet = self.alphabet(other);
+        // The empty path is not a path, so start as if after a trailing `/`
+        let start = (self.start(), other.start(), true);
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some((state, other_state, trailing_slash)) = queue.pop_front() {
+            if !trailing_slash && self.accepts(&state) && other.accepts(&other_state) {
+                return true;
+            }
+            for &c in &alphabet {
+                let next = (self.step(&state, c), other.step(&other_state, c), c == '/');
+                if !next.0.is_empty() && !next.1.is_empty() && seen.insert(next.clone()) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+impl Pattern {
+    /// Check if this pattern matches every path `other` matches, with the same
+    /// negation, so that `other` is redundant next to it.
+    ///
+    /// Size conditions must be at least as loose. Patterns whose matching can
+    /// not be modelled exactly (`&` preservation, unusual `[...]` classes) only
+    /// subsume identical patterns.
+    pub fn subsumes(&self, other: &Pattern) -> bool {
+        if self.is_negated() != other.is_negated() {
+            return false;
+        }
+        if self.original == other.original {
+            return true;
+        }
+
+        let sizes_ok = match (self.size_condition(), other.size_condition()) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some((SizeComparison::GreaterThan, a)), Some((SizeComparison::GreaterThan, b))) => a <= b,
+            (Some((SizeComparison::LessThan, a)), Some((SizeComparison::LessThan, b))) => a >= b,
+            _ => false,
+        };
+
+        sizes_ok && match (Language::of(self), Language::of(other)) {
+            (Some(outer), Some(inner)) => outer.includes(&inner),
+            _ => false,
+        }
+    }
+
+    /// Check if some path is matched by both patterns, ignoring negation and
+    /// size conditions.
+    ///
+    /// Returns `None` when either pattern cannot be modelled exactly.
+    pub(crate) fn overlaps(&self, other: &Pattern) -> Option<bool> {
+        Some(Language::of(self)?.intersects(&Language::of(other)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subsumes(a: &str, b: &str) -> bool {
+        Pattern::new(a).subsumes(&Pattern::new(b))
+    }
+
+    #[test]
+    fn test_subsumes() {
+        assert!(subsumes("*.log", "debug.log"));
+        assert!(subsumes("build/", "/build/"));
+        assert!(subsumes("logs/", "logs/*"));
+        assert!(subsumes("*", "src/*.rs"));
+        assert!(subsumes("*.[ch]", "*.h"));
+        assert!(subsumes("file*.txt", "file[0-9]?.txt"));
+        assert!(subsumes("!*.log", "!keep.log"));
+        assert!(subsumes("*.bin", "size:>10MB *.bin"));
+        assert!(subsumes("size:>1MB *.bin", "size:>10MB data*.bin"));
+
+        assert!(!subsumes("debug.log", "*.log"));
+        assert!(!subsumes("/build/", "build/"));
+        assert!(!subsumes("logs/*", "logs/"));
+        assert!(!subsumes("*.log", "!debug.log"));
+        assert!(!subsumes("size:>10MB *.bin", "*.bin"));
+        assert!(!subsumes("size:<1MB *.bin", "size:<10MB *.bin"));
+        assert!(!subsumes("*.[ch]", "*.c?"));
+        assert!(!subsumes("a*", "*a"));
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let overlaps = |a: &str, b: &str| Pattern::new(a).overlaps(&Pattern::new(b));
+        assert_eq!(overlaps("docs/", "!*.txt"), Some(true));
+        assert_eq!(overlaps("*.log", "!debug.log"), Some(true));
+        assert_eq!(overlaps("a*", "*b"), Some(true));
+        assert_eq!(overlaps("*.log", "!*.tmp"), Some(false));
+        assert_eq!(overlaps("debug.log", "!*.txt"), Some(false));
+        assert_eq!(overlaps("&node_modules/", "!x"), None);
+    }
+
+    #[test]
+    fn test_subsumption_agrees_with_matching() {
+        let pairs = [("*.log", "debug.log"), ("build/", "/build/"), ("logs/", "logs/*"), ("src/*", "*.rs"), ("?[ab", "?a")];
+        let paths = ["debug.log", "a/debug.log", "build", "/build/x", "x/build/y", "logs/a", "src/main.rs", "lib.rs", "xa"];
+        for (outer, inner) in pairs {
+            let (outer_pattern, inner_pattern) = (Pattern::new(outer), Pattern::new(inner));
+            if outer_pattern.subsumes(&inner_pattern) {
+                for path in paths {
+                    assert!(!inner_pattern.matches(path) || outer_pattern.matches(path), "{} {} {}", outer, inner, path);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,671 @@
+// Module: ignore/syntax.rs
+//
+// This module implements a lossless concrete syntax tree for .DotIgnore files.
+// Every byte of the input (comments, blank lines, indentation, line endings and
+// `# end name` markers) is kept, so printing an unmodified tree reproduces the
+// original text exactly.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+use super::diagnostic::Diagnostic;
+use super::compose::{self, GroupReference};
+use super::names;
+use super::parser::{IgnoreParser, GROUP_END_RE, GROUP_START_RE, PROFILE_DIRECTIVE_RE, SET_DIRECTIVE_RE, USE_DIRECTIVE_RE};
+use super::variables::{self, Variables};
+use super::version::{Feature, FormatVersion};
+use super::{DotIgnore, Pattern, PatternGroup};
+
+/// UTF-8 byte order mark
+const BOM: char = '\u{feff}';
+
+/// Line terminator of a single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Last line of a file without a trailing newline
+    None,
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    /// Text of the terminator
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::None => "",
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// A single physical line, stored verbatim
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxLine {
+    /// Text of the line without its terminator
+    pub text: String,
+    /// Terminator that followed the line
+    pub ending: LineEnding,
+}
+
+impl SyntaxLine {
+    /// Create a line with the given text and terminator
+    pub fn new(text: impl Into<String>, ending: LineEnding) -> Self {
+        Self {
+            text: text.into(),
+            ending,
+        }
+    }
+
+    /// Split a raw line (as returned by `BufRead::read_line`) into text and terminator
+    pub fn from_raw(raw: &str) -> Self {
+        if let Some(text) = raw.strip_suffix("\r\n") {
+            Self::new(text, LineEnding::CrLf)
+        } else if let Some(text) = raw.strip_suffix('\n') {
+            Self::new(text, LineEnding::Lf)
+        } else {
+            Self::new(raw, LineEnding::None)
+        }
+    }
+
+    /// Leading whitespace of the line
+    pub fn indent(&self) -> &str {
+        let content_start = self.text.len() - self.text.trim_start().len();
+        &self.text[..content_start]
+    }
+
+    /// Text of the line without surrounding whitespace
+    pub fn trimmed(&self) -> &str {
+        self.text.trim()
+    }
+}
+
+impl fmt::Display for SyntaxLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)?;
+        f.write_str(self.ending.as_str())
+    }
+}
+
+/// A node of the syntax tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxNode {
+    /// Empty or whitespace-only line
+    Blank(SyntaxLine),
+    /// Full-line comment
+    Comment(SyntaxLine),
+    /// `@` directive such as `@profile dev`, `@set NAME = value` or `@use build`
+    Directive(SyntaxLine),
+    /// Ignore pattern
+    Pattern(SyntaxLine),
+    /// `[name] { ... }` group
+    Group(GroupNode),
+    /// `}` with no open group
+    UnexpectedEnd(SyntaxLine),
+}
+
+impl SyntaxNode {
+    /// Number of physical lines covered by this node
+    pub fn line_count(&self) -> usize {
+        match self {
+            SyntaxNode::Group(group) => group.line_count(),
+            _ => 1,
+        }
+    }
+
+    /// Compile this node into a `Pattern`, if it is a pattern line
+    pub fn to_pattern(&self) -> Option<Pattern> {
+        match self {
+            SyntaxNode::Pattern(line) => Some(Pattern::new(&line.text)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxNode::Blank(line)
+            | SyntaxNode::Comment(line)
+            | SyntaxNode::Directive(line)
+            | SyntaxNode::Pattern(line)
+            | SyntaxNode::UnexpectedEnd(line) => line.fmt(f),
+            SyntaxNode::Group(group) => group.fmt(f),
+        }
+    }
+}
+
+/// A group with its header, body and optional closing line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupNode {
+    /// Name parsed from the header
+    pub name: String,
+    /// Group named in an `extends [parent]` clause of the header
+    pub parent: Option<String>,
+    /// `[name] {` line
+    pub header: SyntaxLine,
+    /// Lines between the header and the closing brace
+    pub body: Vec<SyntaxNode>,
+    /// `} # end name` line, missing for unclosed groups
+    pub end: Option<SyntaxLine>,
+}
+
+impl GroupNode {
+    /// Number of physical lines covered by this group
+    pub fn line_count(&self) -> usize {
+        1 + self.body.len() + usize::from(self.end.is_some())
+    }
+
+    /// Compile the pattern lines of this group
+    pub fn patterns(&self) -> Vec<Pattern> {
+        self.body.iter().filter_map(SyntaxNode::to_pattern).collect()
+    }
+
+    /// Name given in the `# end name` marker, if any
+    pub fn end_marker(&self) -> Option<&str> {
+        let end = self.end.as_ref()?;
+        GROUP_END_RE.captures(&end.text)?.get(1).map(|m| m.as_str())
+    }
+
+    /// Header line in canonical form: `[name] {` or `[name] extends [parent] {`,
+    /// quoting names that are not plain identifiers
+    pub fn canonical_header(&self) -> String {
+        match &self.parent {
+            Some(parent) => format!("[{}] extends [{}] {{", names::quote(&self.name), names::quote(parent)),
+            None => format!("[{}] {{", names::quote(&self.name)),
+        }
+    }
+
+    /// Check the names in the header, which the tree keeps even when malformed
+    fn validate_header(&self, first_line: usize) -> Result<()> {
+        if let Some(captures) = GROUP_START_RE.captures(&self.header.text) {
+            for raw in captures.iter().skip(1).flatten() {
+                names::parse_group_name(raw.as_str()).map_err(|e| anyhow!("{} at line {}", e, first_line))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Map this node to a `PatternGroup` using the current format version.
+    ///
+    /// `first_line` is the 1-based line number of the header, used in errors.
+    /// References to other groups are not resolved, since only this group is known.
+    pub fn to_pattern_group(&self, first_line: usize) -> Result<PatternGroup> {
+        self.lower(first_line, &mut Lowering::new(FormatVersion::CURRENT))
+    }
+
+    fn lower(&self, first_line: usize, lowering: &mut Lowering) -> Result<PatternGroup> {
+        self.validate_header(first_line)?;
+
+        let mut group = PatternGroup::new(self.name.clone());
+        let mut line_number = first_line;
+        let index = lowering.group_count;
+        lowering.group_count += 1;
+
+        if let Some(parent) = &self.parent {
+            if lowering.version < Feature::GroupComposition.min_version() {
+                return Err(anyhow!(
+                    "`extends` requires dotignore format {} but the file is format {} at line {}",
+                    Feature::GroupComposition.min_version(), lowering.version, first_line
+                ));
+            }
+            group.parent = Some(parent.clone());
+            lowering.references.push(GroupReference::new(index, 0, parent, first_line));
+        }
+
+        // Variables set inside a group are only visible until its end
+        let outer_variables = lowering.variables.clone();
+        lowering.group = Some(self.name.clone());
+
+        for node in &self.body {
+            line_number += 1;
+
+            match node {
+                SyntaxNode::Pattern(line) => {
+                    if let Some(pattern) = lowering.pattern(line, line_number) {
+                        group.add_pattern(pattern);
+                    }
+                }
+                SyntaxNode::Directive(line) => {
+                    lowering.check_directive(directive_feature(line), line_number)?;
+                    if let Some(captures) = SET_DIRECTIVE_RE.captures(&line.text) {
+                        lowering.define(&captures[1], line_number);
+                    } else if let Some(captures) = USE_DIRECTIVE_RE.captures(&line.text) {
+                        for target in IgnoreParser::parse_group_list(&captures[1], line_number)? {
+                            let position = group.patterns.len();
+                            lowering.references.push(GroupReference::new(index, position, &target, line_number));
+                        }
+                    } else {
+                        let captures = PROFILE_DIRECTIVE_RE.captures(&line.text)
+                            .ok_or_else(|| anyhow!("Unknown directive at line {}", line_number))?;
+                        group.profiles.extend(IgnoreParser::parse_profile_list(&captures[1], line_number)?);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        lowering.variables = outer_variables;
+        lowering.group = None;
+        Ok(group)
+    }
+}
+
+impl fmt::Display for GroupNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.header.fmt(f)?;
+        for node in &self.body {
+            node.fmt(f)?;
+        }
+        if let Some(end) = &self.end {
+            end.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Lossless syntax tree of a whole .DotIgnore file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreDocument {
+    /// Whether the input started with a UTF-8 byte order mark
+    pub bom: bool,
+    /// Top-level nodes in file order
+    pub nodes: Vec<SyntaxNode>,
+}
+
+impl IgnoreDocument {
+    /// Parse text into a syntax tree. This never fails: lines the parser
+    /// cannot place are kept as error nodes and reported by `to_dotignore`.
+    pub fn parse(content: &str) -> Self {
+        let mut builder = DocumentBuilder::new();
+        let mut rest = content;
+
+        while !rest.is_empty() {
+            let end = rest.find('\n').map(|i| i + 1).unwrap_or(rest.len());
+            builder.push_line(SyntaxLine::from_raw(&rest[..end]));
+            rest = &rest[end..];
+        }
+
+        builder.finish()
+    }
+
+    /// Groups in file order
+    pub fn groups(&self) -> impl Iterator<Item = &GroupNode> {
+        self.nodes.iter().filter_map(|node| match node {
+            SyntaxNode::Group(group) => Some(group),
+            _ => None,
+        })
+    }
+
+    /// Patterns outside of any group
+    pub fn patterns(&self) -> Vec<Pattern> {
+        self.nodes.iter().filter_map(SyntaxNode::to_pattern).collect()
+    }
+
+    /// Format version declared by a `#! dotignore <version>` header on the first line
+    pub fn declared_version(&self) -> Result<Option<FormatVersion>> {
+        match self.nodes.first() {
+            Some(SyntaxNode::Comment(line)) => FormatVersion::from_header(&line.text).transpose(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Format version the document is parsed with; files without a header are 1.1
+    pub fn version(&self) -> Result<FormatVersion> {
+        Ok(self.declared_version()?.unwrap_or(FormatVersion::UNVERSIONED))
+    }
+
+    /// Lower the syntax tree into a `DotIgnore`
+    pub fn to_dotignore(&self) -> Result<DotIgnore> {
+        self.lower_with_lines().map(|(dotignore, _)| dotignore)
+    }
+
+    /// Lower the syntax tree, also returning every pattern written on a line
+    /// (as opposed to inherited or `@use`d) with its line number
+    pub(crate) fn lower_with_lines(&self) -> Result<(DotIgnore, Vec<WrittenPattern>)> {
+        let version = self.version()?;
+        if version.major > FormatVersion::CURRENT.major {
+            return Err(anyhow!(
+                "File requires dotignore format {}, but this build only supports up to {}",
+                version, FormatVersion::CURRENT
+            ));
+        }
+
+        let mut lowering = Lowering::new(version);
+        if !version.is_supported() {
+            lowering.diagnostics.push(Diagnostic::warning(Some(1), format!(
+                "File declares dotignore format {}, newer than the supported {}; some rules may be misread",
+                version, FormatVersion::CURRENT
+            )));
+        }
+
+        let mut dotignore = DotIgnore::new();
+        let mut line_number = 1;
+        let mut group_lines: HashMap<&str, usize> = HashMap::new();
+
+        for node in &self.nodes {
+            match node {
+                SyntaxNode::Pattern(line) => {
+                    if let Some(pattern) = lowering.pattern(line, line_number) {
+                        dotignore.add_pattern(pattern);
+                    }
+                }
+                SyntaxNode::Group(group) => {
+                    let pattern_group = group.lower(line_number, &mut lowering)?;
+                    if let Some(first) = group_lines.insert(&group.name, line_number) {
+                        return Err(anyhow!(
+                            "Group '{}' at line {} is already defined at line {}",
+                            group.name, line_number, first
+                        ));
+                    }
+                    dotignore.add_group(pattern_group);
+                }
+                SyntaxNode::Directive(line) => {
+                    lowering.check_directive(directive_feature(line), line_number)?;
+                    if let Some(captures) = SET_DIRECTIVE_RE.captures(&line.text) {
+                        lowering.define(&captures[1], line_number);
+                    } else if USE_DIRECTIVE_RE.is_match(&line.text) {
+                        return Err(anyhow!("@use reference outside of a group at line {}", line_number));
+                    } else {
+                        return Err(anyhow!("@profile declaration outside of a group at line {}", line_number));
+                    }
+                }
+                SyntaxNode::UnexpectedEnd(_) => {
+                    return Err(anyhow!("Unexpected group end at line {}", line_number));
+                }
+                SyntaxNode::Comment(line) => {
+                    if line_number > 1 && FormatVersion::from_header(&line.text).is_some() {
+                        lowering.diagnostics.push(Diagnostic::warning(
+                            Some(line_number),
+                            "Version header is only recognised on the first line",
+                        ));
+                    }
+                }
+                SyntaxNode::Blank(_) => {}
+            }
+
+            line_number += node.line_count();
+        }
+
+        compose::resolve(&mut dotignore.groups, &lowering.references)?;
+
+        dotignore.version = version;
+        dotignore.diagnostics = lowering.diagnostics;
+        Ok((dotignore, lowering.written))
+    }
+}
+
+/// A pattern as written on one line of a document, after variable expansion
+#[derive(Debug, Clone)]
+pub(crate) struct WrittenPattern {
+    /// Group the line is in, `None` outside groups
+    pub group: Option<String>,
+    /// 1-based line number
+    pub line: usize,
+    pub pattern: Pattern,
+}
+
+/// State carried while lowering a syntax tree into a `DotIgnore`
+struct Lowering {
+    version: FormatVersion,
+    diagnostics: Vec<Diagnostic>,
+    /// Group being lowered, `None` outside groups
+    group: Option<String>,
+    /// Patterns compiled from pattern lines so far
+    written: Vec<WrittenPattern>,
+    variables: Variables,
+    /// `extends` and `@use` references, resolved once every group is lowered
+    references: Vec<GroupReference>,
+    /// Number of groups lowered so far
+    group_count: usize,
+}
+
+impl Lowering {
+    fn new(version: FormatVersion) -> Self {
+        Self {
+            version,
+            diagnostics: Vec::new(),
+            group: None,
+            written: Vec::new(),
+            variables: Variables::new(),
+            references: Vec::new(),
+            group_count: 0,
+        }
+    }
+
+    /// Compile a pattern line, expanding variable references first.
+    ///
+    /// Returns `None` (and reports an error) if a reference cannot be expanded.
+    fn pattern(&mut self, line: &SyntaxLine, line_number: usize) -> Option<Pattern> {
+        let pattern = if !variables::has_references(&line.text) || !self.allows(Feature::Variables, line_number) {
+            Pattern::new(&line.text)
+        } else {
+            match self.variables.expand(&line.text) {
+                Ok(text) => Pattern::new(&text),
+                Err(e) => {
+                    self.diagnostics.push(Diagnostic::error(Some(line_number), e.to_string()));
+                    return None;
+                }
+            }
+        };
+
+        self.written.push(WrittenPattern { group: self.group.clone(), line: line_number, pattern: pattern.clone() });
+        Some(pattern)
+    }
+
+    /// Handle the body of a `@set` line
+    fn define(&mut self, definition: &str, line_number: usize) {
+        if let Err(e) = self.variables.define(definition) {
+            self.diagnostics.push(Diagnostic::error(Some(line_number), e.to_string()));
+        }
+    }
+
+    /// Check if the declared version enables `feature`, warning when it does not
+    fn allows(&mut self, feature: Feature, line_number: usize) -> bool {
+        if self.version >= feature.min_version() {
+            return true;
+        }
+
+        self.diagnostics.push(Diagnostic::warning(Some(line_number), format!(
+            "{} require dotignore format {} but the file is format {}; read literally",
+            feature.description(), feature.min_version(), self.version
+        )));
+        false
+    }
+
+    /// Check that the declared version enables the directive of `feature`.
+    ///
+    /// A directive the version does not support is an error: read literally,
+    /// `@profile` would leave its group always active, `@use` would drop the
+    /// rules it brings in and `@set` would become a pattern.
+    fn check_directive(&self, feature: Feature, line_number: usize) -> Result<()> {
+        if self.version < feature.min_version() {
+            return Err(anyhow!(
+                "{} require dotignore format {} but the file is format {} at line {}; declare the version with a '{}' header",
+                feature.description(), feature.min_version(), self.version, line_number, FormatVersion::CURRENT.header()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Feature a directive line relies on
+pub(crate) fn directive_feature(line: &SyntaxLine) -> Feature {
+    if SET_DIRECTIVE_RE.is_match(&line.text) {
+        Feature::Variables
+    } else if USE_DIRECTIVE_RE.is_match(&line.text) {
+        Feature::GroupComposition
+    } else {
+        Feature::ProfileDirective
+    }
+}
+
+impl fmt::Display for IgnoreDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bom {
+            write!(f, "{}", BOM)?;
+        }
+        for node in &self.nodes {
+            node.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Incremental builder that turns a sequence of lines into an `IgnoreDocument`
+pub(crate) struct DocumentBuilder {
+    document: IgnoreDocument,
+    current_group: Option<GroupNode>,
+    first_line: bool,
+}
+
+impl DocumentBuilder {
+    pub(crate) fn new() -> Self {
+        Self {
+            document: IgnoreDocument::default(),
+            current_group: None,
+            first_line: true,
+        }
+    }
+
+    /// Add the next physical line of the input
+    pub(crate) fn push_line(&mut self, mut line: SyntaxLine) {
+        if self.first_line {
+            self.first_line = false;
+            if let Some(text) = line.text.strip_prefix(BOM) {
+                line.text = text.to_string();
+                self.document.bom = true;
+            }
+        }
+
+        let trimmed = line.text.trim();
+
+        let node = if trimmed.is_empty() {
+            SyntaxNode::Blank(line)
+        } else if trimmed.starts_with('#') {
+            SyntaxNode::Comment(line)
+        } else if PROFILE_DIRECTIVE_RE.is_match(&line.text)
+            || SET_DIRECTIVE_RE.is_match(&line.text)
+            || USE_DIRECTIVE_RE.is_match(&line.text)
+        {
+            SyntaxNode::Directive(line)
+        } else if let Some(captures) = GROUP_START_RE.captures(&line.text) {
+            // Malformed names are kept as written and reported when lowering
+            let decode = |raw: &str| names::parse_group_name(raw).unwrap_or_else(|_| raw.to_string());
+            let name = decode(&captures[1]);
+            let parent = captures.get(2).map(|m| decode(m.as_str()));
+            self.close_group();
+            self.current_group = Some(GroupNode {
+                name,
+                parent,
+                header: line,
+                body: Vec::new(),
+                end: None,
+            });
+            return;
+        } else if GROUP_END_RE.is_match(&line.text) {
+            match self.current_group.take() {
+                Some(mut group) => {
+                    group.end = Some(line);
+                    self.document.nodes.push(SyntaxNode::Group(group));
+                    return;
+                }
+                None => SyntaxNode::UnexpectedEnd(line),
+            }
+        } else {
+            SyntaxNode::Pattern(line)
+        };
+
+        match &mut self.current_group {
+            Some(group) => group.body.push(node),
+            None => self.document.nodes.push(node),
+        }
+    }
+
+    /// Finish building, closing any group left open at the end of the input
+    pub(crate) fn finish(mut self) -> IgnoreDocument {
+        self.close_group();
+        self.document
+    }
+
+    fn close_group(&mut self) {
+        if let Some(group) = self.current_group.take() {
+            self.document.nodes.push(SyntaxNode::Group(group));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = [
+            "",
+            "*.log",
+            "# comment\n\n*.tmp\n",
+            "\u{feff}[build] {\r\n\t*.o\r\n   \r\n    # keep\r\n} # end build\r\n",
+            "}\n[a] {\n  x\n[b]   {\n    y",
+            "[unclosed] {\n    @profile dev\n    *.obj\n\n\n",
+        ];
+
+        for input in inputs {
+            let document = IgnoreDocument::parse(input);
+            assert_eq!(document.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors() {
+        let content = "*.tmp\n\n[build] {\n    # artifacts\n    *.o\n    !keep.o\n} # end build\n";
+        let document = IgnoreDocument::parse(content);
+
+        assert_eq!(document.patterns().len(), 1);
+
+        let build = document.groups().next().unwrap();
+        assert_eq!(build.name, "build");
+        assert_eq!(build.end_marker(), Some("build"));
+        assert_eq!(build.header.indent(), "");
+        assert_eq!(build.body[1], SyntaxNode::Pattern(SyntaxLine::new("    *.o", LineEnding::Lf)));
+
+        let group = build.to_pattern_group(3).unwrap();
+        assert_eq!(group.patterns.len(), 2);
+        assert!(group.patterns[1].is_negated());
+
+        let dotignore = document.to_dotignore().unwrap();
+        assert!(dotignore.is_ignored("main.o"));
+        assert!(!dotignore.is_ignored("keep.o"));
+    }
+
+    #[test]
+    fn test_errors_report_line_numbers() {
+        let document = IgnoreDocument::parse("[a] {\n    x\n}\n\n}\n");
+        let error = document.to_dotignore().unwrap_err();
+        assert_eq!(error.to_string(), "Unexpected group end at line 5");
+    }
+
+    #[test]
+    fn test_group_names() {
+        let document = IgnoreDocument::parse("[Archivos_temporales] {\n    *.tmp\n}\n[\"build output\"] {\n    out/\n}\n");
+        let dotignore = document.to_dotignore().unwrap();
+        assert_eq!(dotignore.groups[0].name, "Archivos_temporales");
+        assert_eq!(dotignore.groups[1].name, "build output");
+        assert_eq!(document.groups().nth(1).unwrap().canonical_header(), "[\"build output\"] {");
+
+        // A header with a malformed name is still a group, so its `}` is not reported instead
+        let error = IgnoreDocument::parse("[Archivos temporales] {\n    *.tmp\n}\n").to_dotignore().unwrap_err();
+        assert!(error.to_string().starts_with("Group name 'Archivos temporales' contains spaces"), "{}", error);
+        assert!(error.to_string().ends_with("at line 1"));
+
+        let error = IgnoreDocument::parse("[platform:beos] {\n}\n").to_dotignore().unwrap_err();
+        assert!(error.to_string().starts_with("Unknown platform 'beos'"));
+
+        let error = IgnoreDocument::parse("[a] {\n}\n\n[a] {\n}\n").to_dotignore().unwrap_err();
+        assert_eq!(error.to_string(), "Group 'a' at line 4 is already defined at line 1");
+    }
+}
@@ -0,0 +1,175 @@
+// Module: ignore/tree.rs
+//
+// This module describes a set of paths to evaluate rule sets against, either
+// read from a real directory or listed by hand as a virtual tree.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use walkdir::WalkDir;
+
+/// Directories holding version control metadata, which are never evaluated
+pub(crate) const VCS_DIRECTORIES: &[&str] = &[".git", ".svn", ".hg"];
+
+/// A file or directory in a `PathTree`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeEntry {
+    /// Path relative to the tree root, with `/` separators
+    pub path: PathBuf,
+    /// File size in bytes, `None` for a directory
+    pub size: Option<u64>,
+}
+
+impl TreeEntry {
+    /// Create a file entry
+    pub fn file(path: impl Into<PathBuf>, size: u64) -> Self {
+        Self { path: path.into(), size: Some(size) }
+    }
+
+    /// Create a directory entry
+    pub fn dir(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), size: None }
+    }
+
+    /// Check if the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+}
+
+/// The paths of a directory tree, real or virtual
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PathTree {
+    pub entries: Vec<TreeEntry>,
+}
+
+impl PathTree {
+    /// Create an empty tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry to the tree
+    pub fn add(&mut self, entry: TreeEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Read every file and directory below `root`, sorted by name.
+    ///
+    /// Version control metadata directories are skipped.
+    pub fn scan<P: AsRef<Path>>(root: P) -> Result<Self> {
+        let root = root.as_ref();
+        let mut tree = Self::new();
+
+        let walker = WalkDir::new(root).min_depth(1).sort_by_file_name().into_iter()
+            .filter_entry(|entry| !VCS_DIRECTORIES.iter().any(|name| entry.file_name() == *name));
+        for entry in walker {
+            let entry = entry.with_context(|| format!("Failed to walk {}", root.display()))?;
+            let path = relative_path(root, entry.path());
+            let size = if entry.file_type().is_dir() { None } else { Some(entry.metadata()?.len()) };
+            tree.add(TreeEntry { path, size });
+        }
+
+        Ok(tree)
+    }
+
+    /// Read every file and directory below `root` like [`PathTree::scan`], with
+    /// paths relative to `base`, the directory the rules apply from.
+    ///
+    /// Fails if `root` is neither `base` nor below it, since its paths could
+    /// not be matched against the rules.
+    pub fn scan_within<P: AsRef<Path>, Q: AsRef<Path>>(base: P, root: Q) -> Result<Self> {
+        let (base, root) = (base.as_ref(), root.as_ref());
+        let canonical = |path: &Path| path.canonicalize().with_context(|| format!("Failed to read {}", path.display()));
+        let (canonical_base, canonical_root) = (canonical(base)?, canonical(root)?);
+        let prefix = canonical_root.strip_prefix(&canonical_base)
+            .map_err(|_| anyhow!("{} is outside {}, the directory the rules apply from", root.display(), canonical_base.display()))?;
+        let prefix = relative_path(Path::new(""), prefix);
+
+        let mut tree = Self::scan(root)?;
+        if !prefix.as_os_str().is_empty() {
+            for entry in &mut tree.entries {
+                entry.path = format!("{}/{}", prefix.display(), entry.path.display()).into();
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Build a virtual tree from a listing with one path per line.
+    ///
+    /// Directories end with `/`; a file may be followed by a tab and its size
+    /// in bytes (0 otherwise). Blank lines and lines starting with `#` are skipped.
+    pub fn from_listing(listing: &str) -> Result<Self> {
+        let mut tree = Self::new();
+
+        for (index, line) in listing.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = match line.split_once('\t') {
+                Some((path, size)) => {
+                    let size = size.trim().parse()
+                        .map_err(|_| anyhow!("Invalid size '{}' at line {}", size.trim(), index + 1))?;
+                    TreeEntry::file(path, size)
+                }
+                None => match line.strip_suffix('/') {
+                    Some(path) => TreeEntry::dir(path),
+                    None => TreeEntry::file(line, 0),
+                },
+            };
+            tree.add(entry);
+        }
+
+        Ok(tree)
+    }
+
+    /// Number of entries in the tree
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the tree has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Path of `path` relative to `root`, with `/` separators
+fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_listing() {
+        let tree = PathTree::from_listing("# sample\nsrc/\nsrc/main.rs\t120\n\ndebug.log\n").unwrap();
+        assert_eq!(tree.entries, [
+            TreeEntry::dir("src"),
+            TreeEntry::file("src/main.rs", 120),
+            TreeEntry::file("debug.log", 0),
+        ]);
+        assert!(PathTree::from_listing("a.bin\tlarge\n").is_err());
+    }
+
+    #[test]
+    fn test_scan_within() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("project/tmp")).unwrap();
+        std::fs::write(dir.path().join("project/tmp/notes.txt"), "x").unwrap();
+
+        let tree = PathTree::scan_within(dir.path(), dir.path().join("project")).unwrap();
+        assert_eq!(tree.entries, [TreeEntry::dir("project/tmp"), TreeEntry::file("project/tmp/notes.txt", 1)]);
+        assert!(PathTree::scan_within(dir.path().join("project"), dir.path()).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// Module: ignore/validate.rs
+//
+// This module validates many rule files at once, such as every .ignore file
+// in a repository, spreading the work over the available cores.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+use super::tree::VCS_DIRECTORIES;
+use super::{Diagnostic, DotIgnore, Severity};
+
+/// File names `find_ignore_files` looks for unless others are given
+pub const DEFAULT_FILE_NAMES: &[&str] = &[".ignore", ".DotIgnore"];
+
+/// Outcome of validating one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileValidation {
+    pub file: PathBuf,
+    /// Warnings and errors, including one for a file that could not be loaded
+    pub diagnostics: Vec<Diagnostic>,
+    /// Number of groups, `None` if the file could not be loaded
+    pub groups: Option<usize>,
+    /// Groups that apply on this platform with the given profiles
+    pub active_groups: Option<usize>,
+}
+
+impl FileValidation {
+    /// Check if the file loaded without errors
+    pub fn is_valid(&self) -> bool {
+        self.groups.is_some() && !self.diagnostics.iter().any(|d| d.severity == Severity::Error)
+    }
+}
+
+/// Outcome of `validate_files`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValidationReport {
+    /// One entry per file, in the order the files were given
+    pub files: Vec<FileValidation>,
+}
+
+impl ValidationReport {
+    /// Files with at least one error
+    pub fn failed(&self) -> impl Iterator<Item = &FileValidation> {
+        self.files.iter().filter(|file| !file.is_valid())
+    }
+
+    /// Check if every file is valid
+    pub fn is_valid(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+/// Find the files below `root` whose name is one of `names`, sorted by path.
+///
+/// Version control metadata directories are skipped.
+pub fn find_ignore_files<P: AsRef<Path>, S: AsRef<str>>(root: P, names: &[S]) -> Result<Vec<PathBuf>> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+
+    let walker = WalkDir::new(root).sort_by_file_name().into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !VCS_DIRECTORIES.iter().any(|name| entry.file_name() == *name));
+    for entry in walker {
+        let entry = entry.with_context(|| format!("Failed to walk {}", root.display()))?;
+        if entry.file_type().is_file() && names.iter().any(|name| entry.file_name() == name.as_ref()) {
+            files.push(entry.into_path());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Validate one file, counting the groups active with `profiles`
+pub fn validate_file<P: AsRef<Path>, S: AsRef<str>>(file: P, profiles: &[S]) -> FileValidation {
+    let file = file.as_ref();
+    match DotIgnore::load_from_file(file) {
+        Ok(dotignore) => {
+            let diagnostics = dotignore.diagnostics().to_vec();
+            let dotignore = dotignore.with_profile(profiles);
+            FileValidation {
+                file: file.to_path_buf(),
+                diagnostics,
+                groups: Some(dotignore.groups.len()),
+                active_groups: Some(dotignore.active_groups().count()),
+            }
+        }
+        Err(e) => FileValidation {
+            file: file.to_path_buf(),
+            diagnostics: vec![Diagnostic::error(None, format!("{:#}", e))],
+            groups: None,
+            active_groups: None,
+        },
+    }
+}
+
+/// Validate `files` on as many threads as there are cores
+pub fn validate_files<P: AsRef<Path> + Sync, S: AsRef<str> + Sync>(files: &[P], profiles: &[S]) -> ValidationReport {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(files.len());
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; files.len()]);
+
+    // Cada hilo toma el siguiente archivo pendiente, así un archivo grande no retrasa a los demás
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else { break };
+                let validation = validate_file(file, profiles);
+                results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(validation);
+            });
+        }
+    });
+
+    let files = results.into_inner().unwrap_or_else(|e| e.into_inner());
+    ValidationReport { files: files.into_iter().flatten().collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_validate_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/web")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".ignore"), "*.log\n").unwrap();
+        fs::write(root.join("app/.DotIgnore"), "#! dotignore 1.3\n${MISSING}/x\n").unwrap();
+        fs::write(root.join("app/web/rules.ignore"), "#! dotignore 9.0\n").unwrap();
+        fs::write(root.join(".git/.ignore"), "*.tmp\n").unwrap();
+
+        let files = find_ignore_files(root, DEFAULT_FILE_NAMES).unwrap();
+        assert_eq!(files, [root.join(".ignore"), root.join("app/.DotIgnore")]);
+
+        let files = find_ignore_files(root, &[".ignore", "rules.ignore"]).unwrap();
+        let report = validate_files(&files, &[] as &[&str]);
+        assert_eq!(report.files.len(), 2);
+        assert!(report.files[0].is_valid());
+        assert_eq!(report.files[0].groups, Some(0));
+        assert!(!report.files[1].is_valid());
+        assert_eq!(report.files[1].groups, None);
+
+        let report = validate_files(&[root.join(".ignore"), root.join("app/.DotIgnore")], &[] as &[&str]);
+        let failed: Vec<&Path> = report.failed().map(|file| file.file.as_path()).collect();
+        assert_eq!(failed, [root.join("app/.DotIgnore")]);
+        assert_eq!(report.files[1].diagnostics[0].line, Some(2));
+        assert!(!report.is_valid());
+    }
+}
@@ -0,0 +1,152 @@
+// Module: ignore/variables.rs
+//
+// This module implements `@set` variables and the `${NAME}` and
+// `${env:NAME:-default}` references that are expanded in patterns before
+// they are compiled.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // Regex to validate a variable name: BUILD_DIR
+    static ref VARIABLE_NAME_RE: Regex = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap();
+
+    // Regex to split a definition into name and value: BUILD_DIR = target
+    static ref DEFINITION_RE: Regex = Regex::new(r"^([^=\s]+)\s*=\s*(.*)$").unwrap();
+}
+
+/// Variables visible at a point of a .DotIgnore file
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Variables {
+    values: HashMap<String, String>,
+}
+
+impl Variables {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle the `NAME = value` part of a `@set` line; the value is expanded first
+    pub(crate) fn define(&mut self, definition: &str) -> Result<()> {
+        let captures = DEFINITION_RE.captures(definition)
+            .ok_or_else(|| anyhow!("Expected `@set NAME = value`"))?;
+        let name = &captures[1];
+        if !VARIABLE_NAME_RE.is_match(name) {
+            return Err(anyhow!("Invalid variable name '{}'", name));
+        }
+
+        let value = self.expand(&captures[2])?;
+        self.values.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Replace `${NAME}`, `${NAME:-default}` and `${env:NAME:-default}` references.
+    ///
+    /// `$$` stands for a literal `$`; any other `$` is kept as is.
+    pub(crate) fn expand(&self, text: &str) -> Result<String> {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+
+        while let Some(index) = rest.find('$') {
+            output.push_str(&rest[..index]);
+            rest = &rest[index..];
+
+            if let Some(after) = rest.strip_prefix("$$") {
+                output.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("${") {
+                let end = after.find('}')
+                    .ok_or_else(|| anyhow!("Unterminated variable reference '{}'", rest))?;
+                output.push_str(&self.resolve(&after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                output.push('$');
+                rest = &rest[1..];
+            }
+        }
+
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    /// Look up the body of a `${...}` reference
+    fn resolve(&self, reference: &str) -> Result<String> {
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        let value = match name.strip_prefix("env:") {
+            Some(env_name) => {
+                if !VARIABLE_NAME_RE.is_match(env_name) {
+                    return Err(anyhow!("Invalid environment variable name '{}'", env_name));
+                }
+                std::env::var(env_name).ok()
+            }
+            None => {
+                if !VARIABLE_NAME_RE.is_match(name) {
+                    return Err(anyhow!("Invalid variable name '{}'", name));
+                }
+                self.values.get(name).cloned()
+            }
+        };
+
+        value
+            .or_else(|| default.map(str::to_string))
+            .ok_or_else(|| match name.strip_prefix("env:") {
+                Some(env_name) => anyhow!("Undefined environment variable '{}'", env_name),
+                None => anyhow!("Undefined variable '{}'", name),
+            })
+    }
+}
+
+/// Escape `$` so that `Variables::expand` reproduces `text` unchanged
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('$', "$$")
+}
+
+/// Check if `text` would change when expanded
+pub(crate) fn has_references(text: &str) -> bool {
+    text.contains("${") || text.contains("$$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let mut variables = Variables::new();
+        variables.define("BUILD_DIR = target").unwrap();
+        variables.define("OUT=${BUILD_DIR}/out").unwrap();
+
+        assert_eq!(variables.expand("${BUILD_DIR}/**").unwrap(), "target/**");
+        assert_eq!(variables.expand("${OUT}/*.o").unwrap(), "target/out/*.o");
+        assert_eq!(variables.expand("${MISSING:-dist}/").unwrap(), "dist/");
+        assert_eq!(variables.expand("price$5 $${BUILD_DIR}").unwrap(), "price$5 ${BUILD_DIR}");
+        assert_eq!(variables.expand("${env:DOTIGNORE_TEST_SURELY_UNSET:-.cache}/").unwrap(), ".cache/");
+
+        std::env::set_var("DOTIGNORE_TEST_CACHE_DIR", "ci-cache");
+        assert_eq!(variables.expand("${env:DOTIGNORE_TEST_CACHE_DIR:-.cache}").unwrap(), "ci-cache");
+
+        let escaped = escape("a$${b}$");
+        assert_eq!(variables.expand(&escaped).unwrap(), "a$${b}$");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let mut variables = Variables::new();
+        assert_eq!(variables.expand("${NOPE}/").unwrap_err().to_string(), "Undefined variable 'NOPE'");
+        assert_eq!(
+            variables.expand("${env:DOTIGNORE_TEST_SURELY_UNSET}").unwrap_err().to_string(),
+            "Undefined environment variable 'DOTIGNORE_TEST_SURELY_UNSET'"
+        );
+        assert!(variables.expand("${OPEN").is_err());
+        assert!(variables.expand("${bad name}").is_err());
+        assert!(variables.define("1X = y").is_err());
+        assert!(variables.define("NO_VALUE").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+// Module: ignore/version.rs
+//
+// This module defines the versions of the .DotIgnore format and the
+// `#! dotignore <version>` header that declares which one a file uses.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    // Regex to match the version header: #! dotignore 1.1
+    static ref HEADER_RE: Regex = Regex::new(r"^\s*#!\s*dotignore\b\s*(.*?)\s*$").unwrap();
+}
+
+/// Version of the .DotIgnore format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FormatVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl FormatVersion {
+    /// Original format: groups, negations, `&` and `size:` conditions
+    pub const V1_0: FormatVersion = FormatVersion::new(1, 0);
+    /// Adds `@profile` declarations
+    pub const V1_1: FormatVersion = FormatVersion::new(1, 1);
+    /// Adds `@set` variables and `${...}` substitution
+    pub const V1_2: FormatVersion = FormatVersion::new(1, 2);
+    /// Adds `extends` group headers and `@use` references
+    pub const V1_3: FormatVersion = FormatVersion::new(1, 3);
+    /// Version of files without a header: the syntax that shipped before headers existed
+    pub const UNVERSIONED: FormatVersion = FormatVersion::V1_1;
+    /// Newest version this build understands
+    pub const CURRENT: FormatVersion = FormatVersion::V1_3;
+
+    /// Create a version from its parts
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Parse a version header line.
+    ///
+    /// Returns `None` if the line is not a header at all and `Some(Err(_))`
+    /// if it is a header with a malformed version.
+    pub fn from_header(line: &str) -> Option<Result<Self, Error>> {
+        let captures = HEADER_RE.captures(line)?;
+        Some(captures[1].parse())
+    }
+
+    /// Header line declaring this version, without a line terminator
+    pub fn header(&self) -> String {
+        format!("#! dotignore {}", self)
+    }
+
+    /// Check if this build can fully parse files of this version
+    pub fn is_supported(&self) -> bool {
+        *self <= Self::CURRENT
+    }
+}
+
+impl Default for FormatVersion {
+    fn default() -> Self {
+        Self::CURRENT
+    }
+}
+
+impl FromStr for FormatVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (major, minor) = s.split_once('.').unwrap_or((s, "0"));
+        match (major.parse(), minor.parse()) {
+            (Ok(major), Ok(minor)) => Ok(Self::new(major, minor)),
+            _ => Err(anyhow!("Invalid format version: {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Versions are serialised as `"major.minor"` strings
+#[cfg(feature = "serde")]
+impl serde::Serialize for FormatVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FormatVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Syntax that is only recognised from a given format version on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `@profile` declarations inside groups
+    ProfileDirective,
+    /// `@set` definitions and `${...}` references in patterns
+    Variables,
+    /// `[child] extends [parent]` headers and `@use` references
+    GroupComposition,
+}
+
+impl Feature {
+    /// First format version that recognises this syntax
+    pub fn min_version(&self) -> FormatVersion {
+        match self {
+            Feature::ProfileDirective => FormatVersion::V1_1,
+            Feature::Variables => FormatVersion::V1_2,
+            Feature::GroupComposition => FormatVersion::V1_3,
+        }
+    }
+
+    /// Short description used in diagnostics
+    pub fn description(&self) -> &'static str {
+        match self {
+            Feature::ProfileDirective => "@profile declarations",
+            Feature::Variables => "Variables and ${...} references",
+            Feature::GroupComposition => "Group references",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(FormatVersion::from_header("#! dotignore 1.1").unwrap().unwrap(), FormatVersion::V1_1);
+        assert_eq!(FormatVersion::from_header("#!dotignore 2").unwrap().unwrap(), FormatVersion::new(2, 0));
+        assert!(FormatVersion::from_header("#! dotignore one").unwrap().is_err());
+        assert!(FormatVersion::from_header("# dotignore 1.1").is_none());
+        assert!(FormatVersion::from_header("#! dotignorefoo 1.1").is_none());
+
+        assert_eq!(FormatVersion::V1_1.header(), "#! dotignore 1.1");
+        assert!(FormatVersion::V1_0 < FormatVersion::V1_1);
+        assert!(!FormatVersion::new(1, 99).is_supported());
+    }
+}
@@ -0,0 +1,185 @@
+// Module: ignore/walk.rs
+//
+// This module decides the status of every entry of a tree, including
+// directories kept by `&` patterns, and can collapse ignored directories into
+// a single entry so large pruned subtrees are summarised instead of listed.
+
+use std::path::{Path, PathBuf};
+
+use super::tree::PathTree;
+use super::{DotIgnore, Pattern, RuleMatch};
+
+/// What the rules decide for an entry of a walk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum EntryStatus {
+    Tracked,
+    Ignored,
+    /// A directory kept by a `&` pattern, or a directory inside one; the
+    /// files below it are ignored
+    Preserved,
+}
+
+/// Files and bytes below a collapsed directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirSummary {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// A file or directory and the rule that decided it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkEntry {
+    /// Path relative to the tree root, with `/` separators
+    pub path: PathBuf,
+    /// File size in bytes, `None` for a directory
+    pub size: Option<u64>,
+    pub status: EntryStatus,
+    /// Group of the deciding pattern, `None` outside groups or when nothing matched
+    pub group: Option<String>,
+    /// Deciding pattern as written, `None` when nothing matched
+    pub pattern: Option<String>,
+    /// What the directory contains, for an ignored directory whose entries were collapsed
+    pub collapsed: Option<DirSummary>,
+}
+
+impl WalkEntry {
+    /// Check if the entry is a directory
+    pub fn is_dir(&self) -> bool {
+        self.size.is_none()
+    }
+}
+
+/// Outcome of `DotIgnore::walk`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Walk {
+    /// Entries in tree order, every directory before its contents
+    pub entries: Vec<WalkEntry>,
+}
+
+impl Walk {
+    /// Replace every ignored directory whose contents are all ignored with a
+    /// single entry summarising them
+    pub fn collapse(self) -> Self {
+        let mut entries = Vec::with_capacity(self.entries.len());
+        let mut rest = self.entries.into_iter().peekable();
+
+        while let Some(mut entry) = rest.next() {
+            if entry.status != EntryStatus::Ignored || !entry.is_dir() {
+                entries.push(entry);
+                continue;
+            }
+
+            let prefix = format!("{}/", entry.path.to_string_lossy());
+            let contents: Vec<WalkEntry> = std::iter::from_fn(|| rest.next_if(|next| next.path.to_string_lossy().starts_with(&prefix)))
+                .collect();
+            if contents.iter().all(|inner| inner.status == EntryStatus::Ignored) {
+                let files = contents.iter().filter_map(|inner| inner.size);
+                entry.collapsed = Some(DirSummary { files: files.clone().count(), bytes: files.sum() });
+                entries.push(entry);
+            } else {
+                // Something inside is kept or tracked, so the directory cannot be summarised
+                entries.push(entry);
+                entries.extend(Walk { entries: contents }.collapse().entries);
+            }
+        }
+
+        Walk { entries }
+    }
+}
+
+impl DotIgnore {
+    /// Decide the status of every entry of `tree`, which must list every
+    /// directory before its contents as `PathTree::scan` does.
+    ///
+    /// A directory named by a `&` pattern is preserved with the directories
+    /// below it; the files below it are ignored unless a negation re-includes them.
+    pub fn walk(&self, tree: &PathTree) -> Walk {
+        // Compile the directory pattern behind every `&` once
+        let preserves: Vec<(RuleMatch<'_>, Pattern)> = self.rules()
+            .filter(|rule| rule.pattern.is_preserve())
+            .map(|rule| (rule, Pattern::new(rule.pattern.pattern.trim_start()[1..].trim_start())))
+            .collect();
+        let preserving = |dir: &Path| {
+            let dir = dir.to_string_lossy();
+            preserves.iter().find(|(_, target)| !dir.is_empty() && target.matches(&dir)).map(|(rule, _)| *rule)
+        };
+
+        let entries = tree.entries.iter().map(|entry| {
+            let rule = self.matching_rule_with_size(&entry.path, entry.size);
+            // A directory may be preserved itself; a file only by the directory it is in
+            let preserved_by = match entry.size {
+                None => preserving(&entry.path),
+                Some(_) => preserving(entry.path.parent().unwrap_or(Path::new(""))),
+            };
+            let (status, rule) = match (entry.is_dir(), preserved_by, rule) {
+                (true, Some(preserve), _) => (EntryStatus::Preserved, Some(preserve)),
+                (_, _, Some(rule)) if rule.pattern.is_negated() => (EntryStatus::Tracked, Some(rule)),
+                (false, Some(preserve), rule) => (EntryStatus::Ignored, rule.or(Some(preserve))),
+                (_, _, Some(rule)) => (EntryStatus::Ignored, Some(rule)),
+                (_, _, None) => (EntryStatus::Tracked, None),
+            };
+
+            WalkEntry {
+                path: entry.path.clone(),
+                size: entry.size,
+                status,
+                group: rule.and_then(|rule| rule.group).map(|group| group.name.clone()),
+                pattern: rule.map(|rule| rule.pattern.original.clone()),
+                collapsed: None,
+            }
+        });
+
+        Walk { entries: entries.collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ignore::IgnoreParser;
+
+    #[test]
+    fn test_walk() {
+        let content = "*.log\n!keep.log\n[deps] {\n    node_modules/\n}\n[cache] {\n    &cache/\n}\n";
+        let dotignore = IgnoreParser::new().parse_string(content).unwrap();
+        let tree = PathTree::from_listing("\
+cache/
+cache/a.bin\t10
+cache/sub/
+cache/sub/b.bin\t20
+logs/
+logs/app.log\t5
+logs/keep.log\t5
+node_modules/
+node_modules/left-pad/
+node_modules/left-pad/index.js\t100
+node_modules/react.js\t300
+src/main.rs\t50
+").unwrap();
+
+        let walk = dotignore.walk(&tree);
+        let status = |path: &str| walk.entries.iter().find(|entry| entry.path == Path::new(path)).unwrap().status;
+        assert_eq!(status("cache"), EntryStatus::Preserved);
+        assert_eq!(status("cache/sub"), EntryStatus::Preserved);
+        assert_eq!(status("cache/sub/b.bin"), EntryStatus::Ignored);
+        assert_eq!(status("logs/app.log"), EntryStatus::Ignored);
+        assert_eq!(status("logs/keep.log"), EntryStatus::Tracked);
+        assert_eq!(status("src/main.rs"), EntryStatus::Tracked);
+        let cached = &walk.entries[1];
+        assert_eq!((cached.group.as_deref(), cached.pattern.as_deref()), (Some("cache"), Some("&cache/")));
+
+        let collapsed = walk.collapse();
+        let paths: Vec<&Path> = collapsed.entries.iter().map(|entry| entry.path.as_path()).collect();
+        assert_eq!(paths, [
+            "cache", "cache/a.bin", "cache/sub", "cache/sub/b.bin", "logs", "logs/app.log", "logs/keep.log",
+            "node_modules", "src/main.rs",
+        ].map(Path::new));
+        assert_eq!(collapsed.entries[7].collapsed, Some(DirSummary { files: 2, bytes: 400 }));
+        assert_eq!(collapsed.entries[7].group.as_deref(), Some("deps"));
+    }
+}
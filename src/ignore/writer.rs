@@ -0,0 +1,166 @@
+// Module: ignore/writer.rs
+//
+// This module writes a `DotIgnore` back out as .DotIgnore text, for rule sets
+// that were not read from text in the first place (structured files, JSON).
+// It also tells what of a text file such a rewrite leaves out.
+
+use super::names;
+use super::syntax::{directive_feature, SyntaxNode};
+use super::variables;
+use super::{Diagnostic, DotIgnore, Feature, FormatVersion, IgnoreDocument, Pattern, PatternGroup};
+
+impl DotIgnore {
+    /// Write the rules as .DotIgnore text in the current format version.
+    ///
+    /// Parsing the result gives back the same patterns and groups; comments,
+    /// variables and `@use` are not part of a `DotIgnore` and are not written,
+    /// see `IgnoreDocument::conversion_losses`.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", FormatVersion::CURRENT.header());
+
+        if !self.patterns.is_empty() {
+            text.push('\n');
+            for pattern in &self.patterns {
+                text.push_str(&pattern_line(pattern));
+                text.push('\n');
+            }
+        }
+
+        for group in &self.groups {
+            let (parent, patterns) = split_inherited(group, &self.groups);
+            text.push('\n');
+            match parent {
+                Some(parent) => text.push_str(&format!("[{}] extends [{}] {{\n", names::quote(&group.name), names::quote(parent))),
+                None => text.push_str(&format!("[{}] {{\n", names::quote(&group.name))),
+            }
+            if !group.profiles.is_empty() {
+                text.push_str(&format!("    @profile {}\n", group.profiles.join(", ")));
+            }
+            for pattern in patterns {
+                text.push_str("    ");
+                text.push_str(&pattern_line(pattern));
+                text.push('\n');
+            }
+            text.push_str("}\n");
+        }
+
+        text
+    }
+}
+
+/// Split the patterns `group` inherits through `extends` from its own.
+///
+/// Returns the parent to write in the header, or `None` if the inheritance can
+/// not be expressed (unknown parent, or patterns that do not start with the
+/// parent's), along with the patterns to write in the group body.
+pub(crate) fn split_inherited<'a>(group: &'a PatternGroup, groups: &[PatternGroup]) -> (Option<&'a str>, &'a [Pattern]) {
+    let parent = group.parent.as_ref().and_then(|parent| groups.iter().find(|g| &g.name == parent));
+    let Some(parent) = parent else { return (None, &group.patterns) };
+
+    let inherited = parent.patterns.len();
+    let starts_with_parent = group.patterns.len() >= inherited
+        && parent.patterns.iter().zip(&group.patterns).all(|(a, b)| a.original == b.original);
+
+    if starts_with_parent {
+        (group.parent.as_deref(), &group.patterns[inherited..])
+    } else {
+        (None, &group.patterns)
+    }
+}
+
+/// Source text of a pattern, escaped so the current format reads it back unchanged
+fn pattern_line(pattern: &Pattern) -> String {
+    let mut line = pattern.original.clone();
+    if line.starts_with('@') {
+        line.insert(0, '\\');
+    }
+    if variables::has_references(&line) {
+        line = variables::escape(&line);
+    }
+    line
+}
+
+impl IgnoreDocument {
+    /// Warn about what rewriting this document from its `DotIgnore`, with
+    /// `DotIgnore::to_text` or a structured syntax, leaves out: comments,
+    /// `@set` variables (written expanded) and `@use` references (written as
+    /// the patterns they copy). One warning per kind, at its first line.
+    pub fn conversion_losses(&self) -> Vec<Diagnostic> {
+        let (mut comments, mut variables, mut uses) = (Vec::new(), Vec::new(), Vec::new());
+        let mut visit = |line: usize, node: &SyntaxNode| match node {
+            // The version header is written again
+            SyntaxNode::Comment(text) if line > 1 || FormatVersion::from_header(&text.text).is_none() => comments.push(line),
+            SyntaxNode::Directive(text) => match directive_feature(text) {
+                Feature::Variables => variables.push(line),
+                Feature::GroupComposition => uses.push(line),
+                Feature::ProfileDirective => {}
+            },
+            _ => {}
+        };
+
+        let mut line = 1;
+        for node in &self.nodes {
+            visit(line, node);
+            if let SyntaxNode::Group(group) = node {
+                for (index, node) in group.body.iter().enumerate() {
+                    visit(line + 1 + index, node);
+                }
+            }
+            line += node.line_count();
+        }
+
+        let mut losses = Vec::new();
+        if let Some(&first) = comments.first() {
+            losses.push(Diagnostic::warning(Some(first), format!("{} comment line(s) are not carried over", comments.len())));
+        }
+        if let Some(&first) = variables.first() {
+            losses.push(Diagnostic::warning(Some(first), "Variables are not carried over; patterns are written with their values"));
+        }
+        if let Some(&first) = uses.first() {
+            losses.push(Diagnostic::warning(Some(first), "@use is not carried over; groups are written with the patterns it copies"));
+        }
+        losses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ignore::{IgnoreDocument, IgnoreParser};
+
+    #[test]
+    fn test_to_text_round_trip() {
+        let content = "\\@set X = y\ncost$${X}\n[\"build output\"] {\n    *.o\n}\n";
+        let parser = IgnoreParser::new();
+        let original = parser.parse_string(content).unwrap();
+
+        let text = original.to_text();
+        let reloaded = parser.parse_string(&text).unwrap();
+        assert!(reloaded.diagnostics().is_empty(), "{:?}", reloaded.diagnostics());
+        assert_eq!(reloaded.patterns[0].original, "\\@set X = y");
+        assert_eq!(reloaded.patterns[1].original, "cost$${X}");
+        assert_eq!(reloaded.groups[0].name, "build output");
+
+        let composed = "#! dotignore 1.3\n[base] {\n    *.tmp\n}\n[debug] extends [base] {\n    @profile dev\n    *.pdb\n}\n";
+        let original = parser.parse_string(composed).unwrap();
+        let text = original.to_text();
+        assert!(text.contains("[debug] extends [base] {\n    @profile dev\n    *.pdb\n}\n"), "{}", text);
+
+        let reloaded = parser.parse_string(&text).unwrap();
+        assert_eq!(reloaded.groups[1].patterns.len(), 2);
+        assert_eq!(reloaded.groups[1].profiles, ["dev"]);
+    }
+
+    #[test]
+    fn test_conversion_losses() {
+        let content = "#! dotignore 1.3\n@set OUT = dist\n[base] {\n    # Build output\n    ${OUT}/\n}\n# Tools\n[tools] {\n    @use base\n}\n";
+        let losses = IgnoreDocument::parse(content).conversion_losses();
+        let found: Vec<_> = losses.iter().map(|d| (d.line, d.message.as_str())).collect();
+        assert_eq!(found, [
+            (Some(4), "2 comment line(s) are not carried over"),
+            (Some(2), "Variables are not carried over; patterns are written with their values"),
+            (Some(9), "@use is not carried over; groups are written with the patterns it copies"),
+        ]);
+
+        assert!(IgnoreDocument::parse("#! dotignore 1.3\n[a] {\n    @profile dev\n    *.o\n}\n").conversion_losses().is_empty());
+    }
+}
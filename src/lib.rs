@@ -6,7 +6,7 @@ pub mod ignore;
 pub mod converter;
 
 // Re-export key items from modules for standard usage
-pub use ignore::{DotIgnore, IgnoreConverter, ConversionResult, IgnoreParser, Pattern, PatternGroup};
+pub use ignore::{DotIgnore, IgnoreConverter, ConversionResult, IgnoreParser, Pattern, PatternGroup, Platform, IgnoreDocument, FormatVersion, Diagnostic};
 
 // Library version
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -81,8 +81,8 @@ pub mod plugin {
                 rules.patterns.push(pattern.clone());
             }
             
-            // Add patterns from groups
-            for group in &dotignore.groups {
+            // Add patterns from the groups that apply on this platform
+            for group in dotignore.active_groups() {
                 let group_patterns: Vec<Pattern> = group.patterns.clone();
                 rules.groups.insert(group.name.clone(), group_patterns);
             }
@@ -2,235 +2,1645 @@
 //
 // Command-line interface for DotIgnore system
 
-use anyhow::anyhow;
-use clap::{Parser, Subcommand, ArgAction, Command, arg};
-use std::path::PathBuf;
-use std::path::Path;
-use std::process;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::fs;
-use ignore::DotIgnore;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use clap::{ArgGroup, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use ignore::{ConversionResult, Diagnostic, DotIgnore, IgnoreDocument, IgnoreParser};
+use ignore::ignore::{
+    CleanFilter, CleanMode, DEFAULT_FILE_NAMES, EntryStatus, ExportFormat, FileValidation, FormatStyle, GroupOrder, MergePolicy, MergeReport, PathTree,
+    Record, RuleMatch, Severity, SourceMap, StructuredFormat, WalkEntry, find_ignore_files, parse_age, parse_size, unified_diff,
+};
+use serde::Serialize;
+use ignore::ignore::lint::{self, LintConfig};
+
+/// The command ran and every check it made passed
+const EXIT_SUCCESS: u8 = 0;
+/// The command ran but a check failed: lint errors, differences, paths not
+/// ignored, or files `--check` would change
+const EXIT_FAILURE: u8 = 1;
+/// The command could not run: missing or invalid rule file, I/O error
+const EXIT_ERROR: u8 = 3;
+/// Exit status of `check-ignore` on fatal errors, as in git
+const EXIT_FATAL: u8 = 128;
+
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0    success
+  1    a check failed (lint errors, differences, paths not ignored,
+       files that --check would change)
+  2    invalid command line
+  3    the command could not run (missing or invalid rule file, I/O error)
+
+check-ignore uses the exit statuses of git check-ignore (0, 1 and 128).";
 
 #[derive(Parser)]
-#[command(name = "ignore")]
+#[command(name = "ignore", version)]
 #[command(about = "DotIgnore - A unified ignore file format for all version control systems", long_about = None)]
-#[command(version)]
+#[command(after_help = EXIT_STATUS_HELP)]
 struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Flags accepted by every subcommand
+#[derive(Args)]
+struct GlobalArgs {
+    /// Rule file to read or edit
+    #[arg(short, long, global = true, value_name = "FILE", default_value = ".ignore")]
+    file: PathBuf,
+
+    /// Run as if started in DIR; relative paths are resolved against it
+    #[arg(short = 'C', long, global = true, value_name = "DIR", default_value = ".")]
+    root: PathBuf,
+
+    /// Print nothing on standard output; errors still go to standard error
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// When to color the output
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+
+    /// Enable groups scoped to a profile (repeatable, comma separated)
+    #[arg(short, long, global = true, value_delimiter = ',')]
+    profile: Vec<String>,
+
+    /// Output format: text, json (an array of records) or ndjson (one record per line)
+    #[arg(long, global = true, value_enum, value_name = "FORMAT", default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ColorMode {
+    /// Color when standard output is a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Messages for people
+    Text,
+    /// A JSON array of records, written when the command finishes
+    Json,
+    /// One JSON record per line, written as soon as it is known
+    Ndjson,
+}
+
 #[derive(Subcommand)]
 enum Commands {
-    /// Convert ignore files from other VCS formats to .ignore
-    #[command(name = "convert-ignore")]
-    ConvertIgnore {
-        /// Source format (git, svn)
-        #[arg(short = 'f', long = "format")]
+    /// Create a new .ignore file with common patterns
+    Init {
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Check that .ignore files parse and their patterns compile
+    Validate {
+        /// Files to validate, or directories with --recursive [default: the --file, or the root]
+        paths: Vec<PathBuf>,
+
+        /// Validate every rule file found below the given directories
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// File name to look for with --recursive (repeatable) [default: .ignore and .DotIgnore]
+        #[arg(long, value_name = "NAME", requires = "recursive")]
+        name: Vec<String>,
+    },
+
+    /// Convert a .gitignore or .svnignore to .ignore, or switch a rule set between text, TOML and YAML
+    Convert {
+        /// File to read [default: .gitignore or .svnignore, whichever exists]
+        input: Option<PathBuf>,
+
+        /// File to write; its extension picks the syntax unless --to is given
+        #[arg(short, long, value_name = "FILE")]
+        destination: Option<PathBuf>,
+
+        /// Syntax to write: text, toml or yaml
+        #[arg(short, long, value_name = "SYNTAX")]
+        to: Option<String>,
+    },
+
+    /// Write the rules as a .gitignore or .svnignore
+    Export {
+        /// Format to write: git or svn
+        #[arg(short, long, value_name = "FORMAT", default_value = "git")]
+        to: ExportFormat,
+
+        /// File to write [default: .gitignore or .svnignore]
+        #[arg(short, long, value_name = "FILE")]
+        destination: Option<PathBuf>,
+    },
+
+    /// Tell whether paths are ignored; fails unless all of them are
+    Check {
+        /// Paths to check
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Explain which rule decides whether a path is ignored
+    Why {
+        /// Path to explain
+        path: String,
+    },
+
+    /// List the ignored (or tracked) files and directories under the root
+    Ls {
+        /// Directory tree to list
+        #[arg(default_value = ".")]
+        tree: PathBuf,
+
+        /// List paths the rules ignore (the default)
+        #[arg(long, group = "status")]
+        ignored: bool,
+
+        /// List paths the rules do not ignore
+        #[arg(long, group = "status")]
+        tracked: bool,
+
+        /// List directories kept by '&' patterns
+        #[arg(long, group = "status")]
+        preserved: bool,
+
+        /// Show the group and pattern that decided each path
+        #[arg(short = 'g', long)]
+        show_group: bool,
+
+        /// Show file sizes
+        #[arg(short, long)]
+        sizes: bool,
+
+        /// Show ignored directories as one line with their file count and size
+        #[arg(long)]
+        collapse: bool,
+    },
+
+    /// Remove ignored files, like git clean -X; needs --dry-run or --force
+    #[command(group = ArgGroup::new("mode").required(true))]
+    Clean {
+        /// Directory tree to clean
+        #[arg(default_value = ".")]
+        tree: PathBuf,
+
+        /// Only list what would be removed
+        #[arg(short = 'n', long, group = "mode")]
+        dry_run: bool,
+
+        /// Remove the files
+        #[arg(long, group = "mode")]
+        force: bool,
+
+        /// Only remove paths ignored by this group (repeatable)
+        #[arg(short, long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+
+        /// Only remove files larger than SIZE, such as 10MB
+        #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+        larger_than: Option<u64>,
+
+        /// Only remove files last modified more than AGE ago, such as 30d or 12h
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+
+        /// Move files to the trash instead of deleting them
+        #[arg(long)]
+        trash: bool,
+    },
+
+    /// Rewrite .ignore files in canonical form
+    Fmt {
+        /// Files to format [default: the --file]
+        files: Vec<PathBuf>,
+
+        /// Print a diff and fail instead of rewriting files
+        #[arg(long)]
+        check: bool,
+
+        /// Spaces used to indent group bodies
+        #[arg(long, value_name = "WIDTH", default_value_t = 4)]
+        indent: usize,
+
+        /// Sort patterns within each group
+        #[arg(long)]
+        sort: bool,
+
+        /// Always close groups with '} # end name'
+        #[arg(long)]
+        end_markers: bool,
+
+        /// Group order: preserve or alphabetical
+        #[arg(long, value_name = "ORDER", default_value = "preserve")]
+        group_order: GroupOrder,
+    },
+
+    /// Check .ignore files for likely mistakes such as duplicate or shadowed patterns
+    Lint {
+        /// Files to lint [default: the --file]
+        files: Vec<PathBuf>,
+
+        /// Rule levels; defaults to .ignore-lint.toml next to each file
+        #[arg(long, value_name = "FILE")]
+        config: Option<PathBuf>,
+
+        /// Text style: text or github (workflow annotations); ignored with a structured --output
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
         format: String,
+    },
 
-        /// Source file path
-        #[arg(short = 's', long = "source")]
-        source: PathBuf,
+    /// Upgrade .ignore files to the current format version
+    Migrate {
+        /// Files to migrate [default: the --file]
+        files: Vec<PathBuf>,
 
-        /// Destination file path (optional)
-        #[arg(short = 'd', long = "destination")]
+        /// Report files that need migrating instead of rewriting them
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Remove patterns that other patterns already cover, keeping the same meaning
+    Simplify {
+        /// Files to simplify [default: the --file]
+        files: Vec<PathBuf>,
+
+        /// Report redundant patterns and fail instead of rewriting files
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Add patterns to a group, creating the group if needed
+    Add {
+        /// Patterns to add
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Group to add the patterns to
+        #[arg(short, long)]
+        group: String,
+    },
+
+    /// Remove patterns from a group or from the whole file
+    Remove {
+        /// Patterns to remove
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Only remove the patterns from this group
+        #[arg(short, long)]
+        group: Option<String>,
+    },
+
+    /// Move patterns to another group
+    Mv {
+        /// Patterns to move
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Destination group
+        #[arg(short, long, value_name = "GROUP")]
+        to: String,
+
+        /// Group to take the patterns from; required when a pattern is in several groups
+        #[arg(long, value_name = "GROUP")]
+        from: Option<String>,
+    },
+
+    /// Merge two rule sets, combining same-named groups and reporting contradictions
+    Merge {
+        /// First rule set; its side wins with --policy ours
+        ours: PathBuf,
+
+        /// Second rule set; its side wins with --policy theirs
+        theirs: PathBuf,
+
+        /// File to write the merged rules to instead of standard output
+        #[arg(short, long, value_name = "FILE")]
         destination: Option<PathBuf>,
+
+        /// How to settle contradictions: ours, theirs or both
+        #[arg(long, default_value = "both")]
+        policy: MergePolicy,
+    },
+
+    /// Show what changed in meaning between two rule sets, ignoring formatting
+    Diff {
+        /// Rule set before the change
+        old: PathBuf,
+
+        /// Rule set after the change
+        new: PathBuf,
+    },
+
+    /// List files and directories that a rule change would switch between tracked and ignored
+    Impact {
+        /// Rule set before the change
+        #[arg(long, value_name = "FILE")]
+        old: PathBuf,
+
+        /// Rule set after the change
+        #[arg(long, value_name = "FILE")]
+        new: PathBuf,
+
+        /// Directory tree to evaluate
+        #[arg(default_value = ".")]
+        tree: PathBuf,
+    },
+
+    /// Check that two rule sets ignore exactly the same paths in a tree
+    Equiv {
+        /// First rule set
+        a: PathBuf,
+
+        /// Second rule set
+        b: PathBuf,
+
+        /// Directory tree to compare on
+        #[arg(default_value = ".")]
+        tree: PathBuf,
+
+        /// Compare on the paths listed in FILE instead; directories end in '/'
+        #[arg(long, value_name = "FILE")]
+        paths: Option<PathBuf>,
     },
 
-    /// Show information about DotIgnore
-    #[command(name = "info")]
-    Info,
+    /// Show which rules match anything in a tree, and how much each one ignores
+    Coverage {
+        /// Directory tree to walk
+        #[arg(default_value = ".")]
+        tree: PathBuf,
+    },
+
+    /// Debug ignore rules with the interface of git check-ignore
+    CheckIgnore(CheckIgnoreArgs),
 }
 
-fn main() {
-    let matches = Command::new("ignore")
-        .version(env!("CARGO_PKG_VERSION"))
-        .about("DotIgnore - A unified ignore file format for all version control systems")
-        .arg(arg!(-i --input <FILE> "Input .gitignore or .svnignore file"))
-        .arg(arg!(-o --output <FILE> "Output .ignore file"))
-        .arg(arg!(-c --convert "Convert .ignore to other formats").action(ArgAction::SetTrue))
-        .arg(arg!(-f --format <FORMAT> "Target format (git, svn)"))
-        .arg(arg!(-v --validate "Validate a .ignore file").action(ArgAction::SetTrue))
-        .arg(arg!(-n --create "Create a new .ignore file").action(ArgAction::SetTrue))
-        .get_matches();
+#[derive(Args)]
+struct CheckIgnoreArgs {
+    /// Paths to check
+    paths: Vec<String>,
 
-    // Crear un nuevo archivo .ignore
-    if matches.get_flag("create") {
-        let output_path = matches.get_one::<String>("output")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from(".ignore"));
-        
-        println!("Creating new .ignore file at {:?}", output_path);
-        // Usando example_content en lugar de create_default_file
-        match fs::write(&output_path, DotIgnore::example_content()) {
-            Ok(_) => println!("Successfully created default .ignore file"),
+    /// Print the source, line and pattern that decided each path
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Read paths from standard input, one per line
+    #[arg(long)]
+    stdin: bool,
+
+    /// Separate input and output with NUL instead of newlines
+    #[arg(short = 'z')]
+    nul: bool,
+
+    /// Also print paths no pattern matches (with --verbose)
+    #[arg(short, long)]
+    non_matching: bool,
+
+    /// Accepted for compatibility; there is no index to consult
+    #[arg(long)]
+    no_index: bool,
+}
+
+/// Settings shared by every subcommand, taken from the global flags
+struct Context {
+    file: PathBuf,
+    quiet: bool,
+    color: bool,
+    profiles: Vec<String>,
+    output: OutputFormat,
+    /// Name of the running subcommand, written in every record
+    command: String,
+    /// Records held back until the command finishes, with `--output json`
+    records: RefCell<Vec<String>>,
+}
+
+/// Print a message to standard output, unless `--quiet` or a structured `--output` was given
+macro_rules! say {
+    ($ctx:expr) => {
+        if $ctx.is_text() {
+            println!();
+        }
+    };
+    ($ctx:expr, $($arg:tt)*) => {
+        if $ctx.is_text() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Like `say!`, without a newline
+macro_rules! say_raw {
+    ($ctx:expr, $($arg:tt)*) => {
+        if $ctx.is_text() {
+            print!($($arg)*);
+        }
+    };
+}
+
+/// A record payload about one file
+#[derive(Serialize)]
+struct FileRecord<'a, T: Serialize> {
+    file: &'a Path,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// Outcome of validating one file
+#[derive(Serialize)]
+struct ValidationRecord {
+    valid: bool,
+    errors: usize,
+    warnings: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    groups: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_groups: Option<usize>,
+}
+
+impl From<&FileValidation> for ValidationRecord {
+    fn from(validation: &FileValidation) -> Self {
+        let errors = validation.diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+        Self {
+            valid: validation.is_valid(),
+            errors,
+            warnings: validation.diagnostics.len() - errors,
+            groups: validation.groups,
+            active_groups: validation.active_groups,
+        }
+    }
+}
+
+/// Totals of a `validate` run over several files
+#[derive(Serialize)]
+struct ValidationSummary {
+    files: usize,
+    failed: usize,
+}
+
+/// Whether a path is ignored, and the rule that decided it
+#[derive(Serialize, Default)]
+struct PathRecord<'a> {
+    path: &'a str,
+    ignored: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<EntryStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_dir: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    /// Files below a collapsed directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    /// Total size of those files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+}
+
+impl<'a> PathRecord<'a> {
+    fn new(path: &'a str, rule: Option<RuleMatch<'a>>, source_map: &SourceMap) -> Self {
+        Self {
+            path,
+            ignored: rule.is_some_and(|rule| rule.is_ignored()),
+            group: rule.and_then(|rule| rule.group).map(|group| group.name.as_str()),
+            pattern: rule.map(|rule| rule.pattern.original.as_str()),
+            line: rule.and_then(|rule| source_map.line_of(&rule)),
+            ..Default::default()
+        }
+    }
+}
+
+/// A path removed, or to be removed, by `clean`
+#[derive(Serialize)]
+struct RemovalRecord<'a> {
+    path: &'a str,
+    is_dir: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    /// Files inside a directory removed whole
+    #[serde(skip_serializing_if = "Option::is_none")]
+    files: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    /// `false` with --dry-run or when the removal failed
+    removed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Totals of a `clean` run
+#[derive(Serialize)]
+struct CleanSummary {
+    dry_run: bool,
+    mode: CleanMode,
+    files: usize,
+    bytes: u64,
+    failed: usize,
+}
+
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    source_file: &'a Path,
+    destination_file: &'a Path,
+    format: String,
+    pattern_count: usize,
+    skipped: &'a [String],
+}
+
+#[derive(Serialize)]
+struct FormatRecord {
+    changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EditRecord<'a> {
+    action: &'a str,
+    pattern: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<&'a str>,
+    changed: usize,
+}
+
+#[derive(Serialize)]
+struct MergeRecord<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destination_file: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(flatten)]
+    report: &'a MergeReport,
+}
+
+#[derive(Serialize)]
+struct ErrorRecord {
+    message: String,
+}
+
+impl Context {
+    fn new(global: &GlobalArgs, command: &str) -> Self {
+        let color = match global.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        };
+        Self {
+            file: global.file.clone(),
+            quiet: global.quiet,
+            color,
+            profiles: global.profile.clone(),
+            output: global.output,
+            command: command.to_string(),
+            records: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Check if messages for people go to standard output
+    fn is_text(&self) -> bool {
+        !self.quiet && self.output == OutputFormat::Text
+    }
+
+    /// Write a structured record; does nothing with `--output text` or `--quiet`
+    fn emit(&self, kind: &str, data: impl Serialize) -> Result<()> {
+        if self.quiet || self.output == OutputFormat::Text {
+            return Ok(());
+        }
+
+        let record = Record::new(&self.command, kind, data);
+        if self.output == OutputFormat::Ndjson {
+            let mut out = std::io::stdout().lock();
+            writeln!(out, "{}", serde_json::to_string(&record)?)?;
+            // Los consumidores leen registro a registro
+            out.flush()?;
+        } else {
+            self.records.borrow_mut().push(serde_json::to_string_pretty(&record)?);
+        }
+        Ok(())
+    }
+
+    /// Write the records held back with `--output json`
+    fn finish(&self) {
+        if self.output != OutputFormat::Json || self.quiet {
+            return;
+        }
+
+        // Records are serialised one by one to keep their fields in declaration order
+        let records = self.records.borrow();
+        if records.is_empty() {
+            println!("[]");
+            return;
+        }
+        let items: Vec<String> = records.iter().map(|record| format!("  {}", record.replace('\n', "\n  "))).collect();
+        println!("[\n{}\n]", items.join(",\n"));
+    }
+
+    /// Report a diagnostic about `file`: on standard error as text, or as a record
+    fn report(&self, file: &Path, diagnostic: &Diagnostic) -> Result<()> {
+        if self.output == OutputFormat::Text {
+            eprintln!("{}", self.diagnostic(self.location(file, diagnostic), diagnostic));
+        }
+        self.emit("diagnostic", FileRecord { file, data: diagnostic })
+    }
+
+    /// Files named on the command line, or the `--file` when there are none
+    fn files_or_default(&self, files: &[PathBuf]) -> Vec<PathBuf> {
+        if files.is_empty() { vec![self.file.clone()] } else { files.to_vec() }
+    }
+
+    /// Read the tree below `tree`, with paths relative to the current directory
+    /// (the `-C` root) as `check` and `why` take them
+    fn scan(&self, tree: &Path) -> Result<PathTree> {
+        PathTree::scan_within(".", tree)
+    }
+
+    /// Load the `--file` with the enabled profiles
+    fn rules(&self) -> Result<DotIgnore> {
+        self.load(&self.file)
+    }
+
+    /// Load a rule file with the enabled profiles, see `check_diagnostics`
+    fn load(&self, file: &Path) -> Result<DotIgnore> {
+        let dotignore = DotIgnore::load_from_file(file)?;
+        self.check_diagnostics(file, &dotignore)?;
+        Ok(dotignore.with_profile(&self.profiles))
+    }
+
+    /// Load the `--file` with the line of every pattern; structured files have none
+    fn rules_with_source_map(&self) -> Result<(DotIgnore, SourceMap)> {
+        let (dotignore, source_map) = if StructuredFormat::from_path(&self.file).is_some() {
+            (DotIgnore::load_from_file(&self.file)?, SourceMap::default())
+        } else {
+            IgnoreDocument::load_from_file(&self.file)?.to_dotignore_with_source_map()?
+        };
+        self.check_diagnostics(&self.file, &dotignore)?;
+        Ok((dotignore.with_profile(&self.profiles), source_map))
+    }
+
+    /// Report the diagnostics of a loaded rule file, and fail if one is an
+    /// error: the lines behind it were skipped, so the rules are incomplete
+    fn check_diagnostics(&self, file: &Path, dotignore: &DotIgnore) -> Result<()> {
+        for diagnostic in dotignore.diagnostics() {
+            self.report(file, diagnostic)?;
+        }
+        let errors = dotignore.diagnostics().iter().filter(|d| d.severity == Severity::Error).count();
+        if errors > 0 {
+            anyhow::bail!("{} has {} error(s); its rules were not used", file.display(), errors);
+        }
+        Ok(())
+    }
+
+    /// Wrap text in an ANSI color when colors are enabled
+    fn paint(&self, text: impl Display, color: Color) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", color as u8, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Format a diagnostic as `location: severity[code]: message`
+    fn diagnostic(&self, location: impl Display, diagnostic: &Diagnostic) -> String {
+        let color = if diagnostic.severity == Severity::Error { Color::Red } else { Color::Yellow };
+        let label = match &diagnostic.code {
+            Some(code) => format!("{}[{}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        format!("{}: {}: {}", location, self.paint(label, color), diagnostic.message)
+    }
+
+    /// Location of a diagnostic in `file`, as `file:line` when the line is known
+    fn location(&self, file: &Path, diagnostic: &Diagnostic) -> String {
+        match diagnostic.line {
+            Some(line) => format!("{}:{}", file.display(), line),
+            None => file.display().to_string(),
+        }
+    }
+}
+
+/// ANSI color codes
+#[derive(Clone, Copy)]
+enum Color {
+    Red = 31,
+    Green = 32,
+    Yellow = 33,
+}
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let ctx = Context::new(&cli.global, matches.subcommand_name().unwrap_or_default());
+    let status = run_command(&ctx, cli);
+    ctx.finish();
+    status
+}
+
+/// Run the subcommand and turn its outcome into an exit status
+fn run_command(ctx: &Context, cli: Cli) -> ExitCode {
+    // Los errores también se emiten como registro para quien lee la salida estructurada
+    let fail = |message: String, status: u8| {
+        if let Err(e) = ctx.emit("error", ErrorRecord { message: message.clone() }) {
+            eprintln!("error: {}", e);
+        }
+        ExitCode::from(status)
+    };
+
+    // Igual que `git -C`: todas las rutas relativas parten de la raíz indicada
+    if let Err(e) = std::env::set_current_dir(&cli.global.root) {
+        let message = format!("cannot enter {}: {}", cli.global.root.display(), e);
+        eprintln!("{}: {}", ctx.paint("error", Color::Red), message);
+        return fail(message, EXIT_ERROR);
+    }
+
+    // check-ignore conserva los códigos de salida de git
+    if let Commands::CheckIgnore(args) = &cli.command {
+        return match check_ignore(ctx, args) {
+            Ok(true) => ExitCode::from(EXIT_SUCCESS),
+            Ok(false) => ExitCode::from(EXIT_FAILURE),
             Err(e) => {
-                eprintln!("Error creating default .ignore: {}", e);
-                process::exit(1);
+                eprintln!("fatal: {:#}", e);
+                fail(format!("{:#}", e), EXIT_FATAL)
             }
+        };
+    }
+
+    match run(ctx, cli.command) {
+        Ok(true) => ExitCode::from(EXIT_SUCCESS),
+        Ok(false) => ExitCode::from(EXIT_FAILURE),
+        Err(e) => {
+            eprintln!("{}: {:#}", ctx.paint("error", Color::Red), e);
+            fail(format!("{:#}", e), EXIT_ERROR)
         }
-        return;
     }
+}
 
-    // Validar un archivo .ignore existente
-    if matches.get_flag("validate") {
-        let input_path = matches.get_one::<String>("input")
-            .map(PathBuf::from)
-            .unwrap_or_else(|| PathBuf::from(".ignore"));
-            
-        println!("Validating .ignore file at {:?}", input_path);
-        
-        match DotIgnore::load_from_file(&input_path) {
-            Ok(_) => {
-                println!("The .ignore file is valid!");
-                return;
-            },
-            Err(e) => {
-                eprintln!("Error validating .ignore: {}", e);
-                process::exit(1);
+/// Run a subcommand. Returns `false` when it ran but a check failed.
+fn run(ctx: &Context, command: Commands) -> Result<bool> {
+    match command {
+        Commands::Init { force } => init_file(ctx, force).map(|_| true),
+        Commands::Validate { paths, recursive, name } => validate_files(ctx, &paths, recursive, &name),
+        Commands::Convert { input, destination, to } => convert_file(ctx, input, destination, to.as_deref()).map(|_| true),
+        Commands::Export { to, destination } => export_file(ctx, to, destination).map(|_| true),
+        Commands::Check { paths } => check_paths(ctx, &paths),
+        Commands::Why { path } => explain_path(ctx, &path).map(|_| true),
+        Commands::Ls { tree, ignored: _, tracked, preserved, show_group, sizes, collapse } => {
+            let status = match (tracked, preserved) {
+                (true, _) => EntryStatus::Tracked,
+                (_, true) => EntryStatus::Preserved,
+                _ => EntryStatus::Ignored,
+            };
+            list_paths(ctx, &tree, status, ListStyle { show_group, sizes, collapse }).map(|_| true)
+        }
+        Commands::Clean { tree, dry_run, force: _, groups, larger_than, older_than, trash } => {
+            let filter = CleanFilter { groups, larger_than, older_than, ..Default::default() };
+            let mode = if trash { CleanMode::Trash } else { CleanMode::Delete };
+            clean_tree(ctx, &tree, filter, dry_run, mode).map(|_| true)
+        }
+        Commands::Fmt { files, check, indent, sort, end_markers, group_order } => {
+            let style = FormatStyle { indent_width: indent, sort_patterns: sort, end_markers, group_order };
+            format_files(ctx, &files, &style, check)
+        }
+        Commands::Lint { files, config, format } => lint_files(ctx, &files, config.as_deref(), &format),
+        Commands::Migrate { files, check } => migrate_files(ctx, &files, check),
+        Commands::Simplify { files, check } => simplify_files(ctx, &files, check),
+        Commands::Add { patterns, group } => edit_file(ctx, &patterns, Edit::Add(&group)).map(|_| true),
+        Commands::Remove { patterns, group } => edit_file(ctx, &patterns, Edit::Remove(group.as_deref())).map(|_| true),
+        Commands::Mv { patterns, to, from } => edit_file(ctx, &patterns, Edit::Move(from.as_deref(), &to)).map(|_| true),
+        Commands::Merge { ours, theirs, destination, policy } => merge_files(ctx, &ours, &theirs, destination, policy).map(|_| true),
+        Commands::Diff { old, new } => diff_files(ctx, &old, &new).map(|_| true),
+        Commands::Impact { old, new, tree } => impact_report(ctx, &old, &new, &tree).map(|_| true),
+        Commands::Equiv { a, b, tree, paths } => equivalence_report(ctx, &a, &b, &tree, paths.as_deref()),
+        Commands::Coverage { tree } => coverage_report(ctx, &tree).map(|_| true),
+        Commands::CheckIgnore(_) => unreachable!("handled in main with git's exit statuses"),
+    }
+}
+
+/// Run the `init` subcommand
+fn init_file(ctx: &Context, force: bool) -> Result<()> {
+    if ctx.file.exists() && !force {
+        anyhow::bail!("{} already exists; use --force to overwrite it", ctx.file.display());
+    }
+    fs::write(&ctx.file, DotIgnore::example_content())
+        .with_context(|| format!("Failed to write {}", ctx.file.display()))?;
+    say!(ctx, "Created {}", ctx.file.display());
+    ctx.emit("created", FileRecord { file: &ctx.file, data: () })
+}
+
+/// Run the `validate` subcommand. Returns `false` if some file has errors.
+fn validate_files(ctx: &Context, paths: &[PathBuf], recursive: bool, names: &[String]) -> Result<bool> {
+    let files = if recursive {
+        let names: Vec<&str> = if names.is_empty() {
+            DEFAULT_FILE_NAMES.to_vec()
+        } else {
+            names.iter().map(String::as_str).collect()
+        };
+        let roots = if paths.is_empty() { vec![PathBuf::from(".")] } else { paths.to_vec() };
+        let mut files = Vec::new();
+        for root in roots {
+            if !root.is_dir() {
+                anyhow::bail!("{} is not a directory", root.display());
             }
+            // Mostrar `a/.ignore` en lugar de `./a/.ignore`
+            let found = find_ignore_files(&root, &names)?;
+            files.extend(found.into_iter().map(|file| file.strip_prefix(".").map(Path::to_path_buf).unwrap_or(file)));
+        }
+        files
+    } else {
+        let files = ctx.files_or_default(paths);
+        if let Some(missing) = files.iter().find(|file| !file.exists()) {
+            anyhow::bail!("{} does not exist", missing.display());
+        }
+        files
+    };
+
+    // Un archivo que no se puede leer como reglas es un resultado de la validación, no un fallo del comando
+    let report = ignore::ignore::validate_files(&files, &ctx.profiles);
+    for validation in &report.files {
+        for diagnostic in &validation.diagnostics {
+            ctx.report(&validation.file, diagnostic)?;
         }
+        if let (true, Some(groups), Some(active_groups)) = (validation.is_valid(), validation.groups, validation.active_groups) {
+            say!(ctx, "{}: valid, {} of {} group(s) active", validation.file.display(), active_groups, groups);
+        }
+        ctx.emit("file", FileRecord { file: &validation.file, data: ValidationRecord::from(validation) })?;
     }
 
-    // Convertir entre formatos
-    let input_path = matches.get_one::<String>("input").map(PathBuf::from);
-    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
-    
-    // Determinar las rutas de origen y destino
-    let (src_path, dest_path) = if matches.get_flag("convert") {
-        // Modo de conversión de .ignore a otros formatos
-        let src = input_path.unwrap_or_else(|| PathBuf::from(".ignore"));
-        let dest = output_path.unwrap_or_else(|| {
-            // Determinar formato destino
-            let default_format = String::from("git");
-            let format = matches.get_one::<String>("format").unwrap_or(&default_format);
-            match format.to_lowercase().as_str() {
-                "git" => PathBuf::from(".gitignore"),
-                "svn" => PathBuf::from(".svnignore"),
-                _ => {
-                    eprintln!("Unsupported target format: {}", format);
-                    process::exit(1);
+    if recursive {
+        let failed = report.failed().count();
+        if failed == 0 {
+            say!(ctx, "{} file(s) validated, {}", report.files.len(), ctx.paint("all valid", Color::Green));
+        } else {
+            say!(ctx, "{} file(s) validated, {}", report.files.len(), ctx.paint(format!("{} failed", failed), Color::Red));
+        }
+        ctx.emit("summary", ValidationSummary { files: report.files.len(), failed })?;
+    }
+
+    Ok(report.is_valid())
+}
+
+/// Check if a file is another version control system's ignore file
+fn is_vcs_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .is_some_and(|name| name.ends_with("gitignore") || name.ends_with("svnignore"))
+}
+
+/// Run the `convert` subcommand: .gitignore and .svnignore to .ignore, or
+/// between the text syntax and the structured ones (text to TOML by default)
+fn convert_file(ctx: &Context, input: Option<PathBuf>, destination: Option<PathBuf>, to: Option<&str>) -> Result<()> {
+    let input = match input {
+        Some(input) => input,
+        // Auto-detectar formatos comunes
+        None => [".gitignore", ".svnignore"].iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists())
+            .context("No input file given and no .gitignore or .svnignore found")?,
+    };
+    let from_vcs = is_vcs_ignore_file(&input);
+
+    // `None` stands for the text syntax
+    let target = match (to, &destination) {
+        (Some(to), _) if to.eq_ignore_ascii_case("text") => None,
+        (Some(to), _) => Some(to.parse::<StructuredFormat>()?),
+        (None, Some(destination)) => StructuredFormat::from_path(destination),
+        (None, None) if from_vcs || StructuredFormat::from_path(&input).is_some() => None,
+        (None, None) => Some(StructuredFormat::Toml),
+    };
+    let destination = destination.unwrap_or_else(|| {
+        let name = target.map_or(".ignore", |format| format.default_file_name());
+        input.with_file_name(name)
+    });
+    if destination == input {
+        anyhow::bail!("{} is already in the requested syntax", input.display());
+    }
+
+    let result = if from_vcs {
+        // En memoria: el destino se escribe una sola vez, ya en la sintaxis pedida
+        let (text, result) = DotIgnore::convert_to_string(&input, &destination)?;
+        let content = match target {
+            Some(format) => {
+                // Los comentarios del archivo original no caben en TOML ni YAML
+                let comments = fs::read_to_string(&input)?.lines().filter(|line| line.trim_start().starts_with('#')).count();
+                if comments > 0 {
+                    ctx.report(&input, &Diagnostic::warning(None, format!("{} comment line(s) are not carried over", comments)))?;
                 }
+                format.write(&IgnoreParser::new().parse_string(&text)?)?
             }
-        });
-        (src, dest)
+            None => text,
+        };
+        fs::write(&destination, content)?;
+        result
     } else {
-        // Modo de conversión a .ignore
-        let src = if let Some(path) = input_path {
-            path
-        } else {
-            // Auto-detectar formatos comunes
-            if Path::new(".gitignore").exists() {
-                PathBuf::from(".gitignore")
-            } else if Path::new(".svnignore").exists() {
-                PathBuf::from(".svnignore")
-            } else {
-                eprintln!("No input file specified and couldn't find .gitignore or .svnignore");
-                process::exit(1);
+        let dotignore = DotIgnore::load_from_file(&input)?;
+        for diagnostic in dotignore.diagnostics() {
+            ctx.report(&input, diagnostic)?;
+        }
+        // Avisar de lo que la sintaxis de texto tenía y el destino no conserva
+        if StructuredFormat::from_path(&input).is_none() {
+            for loss in IgnoreDocument::parse(&fs::read_to_string(&input)?).conversion_losses() {
+                ctx.report(&input, &loss)?;
             }
+        }
+        let content = match target {
+            Some(format) => format.write(&dotignore)?,
+            None => dotignore.to_text(),
         };
-        
-        let dest = output_path.unwrap_or_else(|| PathBuf::from(".ignore"));
-        (src, dest)
+        fs::write(&destination, content)?;
+
+        let mut platform_patterns = HashMap::new();
+        for group in &dotignore.groups {
+            *platform_patterns.entry(group.name.clone()).or_insert(0) += group.patterns.len();
+        }
+        ConversionResult {
+            source_file: input.clone(),
+            destination_file: destination.clone(),
+            pattern_count: dotignore.patterns.len() + platform_patterns.values().sum::<usize>(),
+            standard_patterns: dotignore.patterns.len(),
+            platform_patterns,
+        }
     };
-    
-    println!("Converting from {:?} to {:?}", src_path, dest_path);
-    
-    // Realizar la conversión
-    let result = if matches.get_flag("convert") {
-        // Usar la función convert_file para cualquier conversión
-        DotIgnore::convert_file(&src_path, Some(&dest_path))
-    } else {
-        // Convertir a .ignore
-        DotIgnore::convert_file(&src_path, Some(&dest_path))
+
+    say!(ctx, "Converted {} to {} ({} pattern(s))", input.display(), destination.display(), result.pattern_count);
+    let mut groups: Vec<_> = result.platform_patterns.iter().collect();
+    groups.sort();
+    for (group, count) in groups {
+        say!(ctx, "  [{}] {} pattern(s)", group, count);
+    }
+    ctx.emit("conversion", &result)
+}
+
+/// Run the `export` subcommand
+fn export_file(ctx: &Context, format: ExportFormat, destination: Option<PathBuf>) -> Result<()> {
+    let destination = destination.unwrap_or_else(|| PathBuf::from(format.default_file_name()));
+    let result = ctx.rules()?.export(format);
+    fs::write(&destination, &result.content)
+        .with_context(|| format!("Failed to write {}", destination.display()))?;
+
+    say!(ctx, "Exported {} to {} ({} pattern(s))", ctx.file.display(), destination.display(), result.pattern_count);
+    if ctx.output == OutputFormat::Text {
+        for skipped in &result.skipped {
+            eprintln!("{}: skipped {}", ctx.paint("warning", Color::Yellow), skipped);
+        }
+    }
+    ctx.emit("export", ExportRecord {
+        source_file: &ctx.file,
+        destination_file: &destination,
+        format: format.to_string(),
+        pattern_count: result.pattern_count,
+        skipped: &result.skipped,
+    })
+}
+
+/// Run the `check` subcommand. Returns `false` unless every path is ignored.
+fn check_paths(ctx: &Context, paths: &[String]) -> Result<bool> {
+    let (dotignore, source_map) = ctx.rules_with_source_map()?;
+    let mut all_ignored = true;
+
+    for path in paths {
+        let record = PathRecord::new(path, dotignore.matching_rule(path), &source_map);
+        all_ignored &= record.ignored;
+        let status = if record.ignored { ctx.paint("ignored", Color::Green) } else { ctx.paint("tracked", Color::Yellow) };
+        say!(ctx, "{}: {}", path, status);
+        ctx.emit("path", record)?;
+    }
+
+    Ok(all_ignored)
+}
+
+/// Describe the rule that decided a path, with its location in the rule file
+fn describe_rule(ctx: &Context, source_map: &SourceMap, rule: &RuleMatch<'_>) -> String {
+    match source_map.line_of(rule) {
+        Some(line) => format!("{} ({}:{})", rule, ctx.file.display(), line),
+        None => format!("{} ({})", rule, ctx.file.display()),
+    }
+}
+
+/// Run the `why` subcommand
+fn explain_path(ctx: &Context, path: &str) -> Result<()> {
+    let (dotignore, source_map) = ctx.rules_with_source_map()?;
+
+    let rule = dotignore.matching_rule(path);
+    match rule {
+        Some(rule) if rule.is_ignored() => {
+            say!(ctx, "{} is {} by {}", path, ctx.paint("ignored", Color::Green), describe_rule(ctx, &source_map, &rule));
+        }
+        Some(rule) => {
+            say!(ctx, "{} is {}: re-included by {}", path, ctx.paint("tracked", Color::Yellow), describe_rule(ctx, &source_map, &rule));
+        }
+        None => say!(ctx, "{} is {}: no rule matches it", path, ctx.paint("tracked", Color::Yellow)),
+    }
+
+    ctx.emit("path", PathRecord::new(path, rule, &source_map))
+}
+
+/// Columns shown by the `ls` subcommand
+struct ListStyle {
+    show_group: bool,
+    sizes: bool,
+    collapse: bool,
+}
+
+/// Run the `ls` subcommand
+fn list_paths(ctx: &Context, tree: &Path, status: EntryStatus, style: ListStyle) -> Result<()> {
+    let (dotignore, source_map) = ctx.rules_with_source_map()?;
+    let mut walk = dotignore.walk(&ctx.scan(tree)?);
+    if style.collapse {
+        walk = walk.collapse();
+    }
+    let entries: Vec<&WalkEntry> = walk.entries.iter().filter(|entry| entry.status == status).collect();
+
+    // Columnas alineadas: ruta (con el resumen del directorio), tamaño y regla
+    let names: Vec<String> = entries.iter()
+        .map(|entry| {
+            let mut name = entry.path.to_string_lossy().into_owned();
+            if entry.is_dir() {
+                name.push('/');
+            }
+            if let Some(summary) = entry.collapsed {
+                name.push_str(&format!(" ({}, {})", format_file_count(summary.files), format_bytes(summary.bytes)));
+            }
+            name
+        })
+        .collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+
+    for (name, entry) in names.iter().zip(&entries) {
+        let mut line = name.clone();
+        if style.sizes {
+            let size = entry.size.map(format_bytes).unwrap_or_default();
+            line = format!("{:<width$}  {:>10}", line, size);
+        }
+        if style.show_group {
+            let rule = match (&entry.group, &entry.pattern) {
+                (Some(group), Some(pattern)) => format!("[{}] {}", group, pattern),
+                (None, Some(pattern)) => pattern.clone(),
+                _ => "no rule".to_string(),
+            };
+            let width = if style.sizes { width + 12 } else { width };
+            line = format!("{:<width$}  {}", line, ctx.paint(rule, Color::Yellow));
+        }
+        say!(ctx, "{}", line.trim_end());
+
+        let path = entry.path.to_string_lossy();
+        ctx.emit("path", PathRecord {
+            path: &path,
+            ignored: entry.status == EntryStatus::Ignored,
+            status: Some(entry.status),
+            is_dir: Some(entry.is_dir()),
+            size: entry.size,
+            group: entry.group.as_deref(),
+            pattern: entry.pattern.as_deref(),
+            line: entry.pattern.as_deref().and_then(|pattern| source_map.line_of_pattern(entry.group.as_deref(), pattern)),
+            files: entry.collapsed.map(|summary| summary.files),
+            bytes: entry.collapsed.map(|summary| summary.bytes),
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Run the `clean` subcommand
+fn clean_tree(ctx: &Context, tree: &Path, mut filter: CleanFilter, dry_run: bool, mode: CleanMode) -> Result<()> {
+    let (dotignore, source_map) = ctx.rules_with_source_map()?;
+    // Los archivos con el nombre del --file tampoco se borran, aunque un patrón los ignore
+    if let Some(name) = ctx.file.file_name() {
+        filter.rule_files.push(name.to_string_lossy().into_owned());
+    }
+
+    let plan = dotignore.plan_clean(".", tree, &filter)?;
+    let report = (!dry_run).then(|| plan.execute(".", mode));
+    let done = match (&report, mode) {
+        (None, _) => "Would remove",
+        (Some(_), CleanMode::Delete) => "Removed",
+        (Some(_), CleanMode::Trash) => "Trashed",
     };
-    
-    match result {
-        Ok(result) => {
-            println!("Conversion successful!");
-            println!("Converted patterns: {}", result.pattern_count);
-        },
-        Err(e) => {
-            eprintln!("Error during conversion: {}", e);
-            process::exit(1);
-        }
-    }
-}
-
-fn check_file(file_path: &str) {
-    let path = Path::new(file_path);
-    
-    if !path.exists() {
-        println!("El archivo no existe: {}", file_path);
-        return;
-    }
-    
-    // Intentar cargar el archivo .DotIgnore
-    match DotIgnore::load_from_file(path) {
-        Ok(ignore) => {
-            println!("Archivo válido: {}", file_path);
-            // No podemos acceder a los grupos directamente ya que son privados
-            println!("Archivo .DotIgnore válido");
-            
-            // Comprobar si algunos archivos comunes serían ignorados
-            let test_files = [
-                "file.txt", "build/output.log", "node_modules/package.json",
-                "tmp/cache.dat", ".DS_Store", "Thumbs.db"
-            ];
-            
-            println!("Comprobando patrones con archivos de ejemplo:");
-            for test_file in test_files {
-                let status = if ignore.is_ignored(test_file) { "ignorado" } else { "no ignorado" };
-                println!("  - {}: {}", test_file, status);
+
+    for entry in &plan.entries {
+        let failure = report.as_ref().and_then(|report| report.failed.iter().find(|failure| failure.path == entry.path));
+        // Lo que se borró de verdad, que puede diferir del plan si el árbol cambió entretanto
+        let entry = report.as_ref().and_then(|report| report.removed.iter().find(|removed| removed.path == entry.path))
+            .unwrap_or(entry);
+        let mut name = entry.path.to_string_lossy().into_owned();
+        if entry.is_dir() {
+            name.push('/');
+        }
+        match (failure, entry.collapsed) {
+            (Some(failure), _) => eprintln!("{}: cannot remove {}: {}", ctx.paint("error", Color::Red), name, failure.message),
+            (None, Some(summary)) => {
+                say!(ctx, "{} {} ({}, {})", done, name, format_file_count(summary.files), format_bytes(summary.bytes));
             }
-        },
-        Err(err) => {
-            println!("Error al cargar el archivo: {}", err);
+            (None, None) => say!(ctx, "{} {} ({})", done, name, format_bytes(entry.size.unwrap_or(0))),
         }
+
+        let path = entry.path.to_string_lossy();
+        ctx.emit("removal", RemovalRecord {
+            path: &path,
+            is_dir: entry.is_dir(),
+            size: entry.size,
+            group: entry.group.as_deref(),
+            pattern: entry.pattern.as_deref(),
+            line: entry.pattern.as_deref().and_then(|pattern| source_map.line_of_pattern(entry.group.as_deref(), pattern)),
+            files: entry.collapsed.map(|summary| summary.files),
+            bytes: entry.collapsed.map(|summary| summary.bytes),
+            removed: report.is_some() && failure.is_none(),
+            error: failure.map(|failure| failure.message.as_str()),
+        })?;
     }
+
+    let (files, bytes, failed) = match &report {
+        Some(report) => (report.files, report.bytes, report.failed.len()),
+        None => (plan.files(), plan.bytes(), 0),
+    };
+    if plan.entries.is_empty() {
+        say!(ctx, "Nothing to clean");
+    } else {
+        let total = format!("{} ({})", format_file_count(files), format_bytes(bytes));
+        match (&report, mode) {
+            (Some(_), CleanMode::Trash) => say!(ctx, "Moved {} to the trash", total),
+            _ => say!(ctx, "{} {}", done, total),
+        }
+    }
+    ctx.emit("clean", CleanSummary { dry_run, mode, files, bytes, failed })?;
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} paths could not be removed", failed, plan.entries.len());
+    }
+    Ok(())
+}
+
+/// Edit made by the `add`, `remove` and `mv` subcommands
+enum Edit<'a> {
+    Add(&'a str),
+    Remove(Option<&'a str>),
+    /// Source group, if given, and destination group
+    Move(Option<&'a str>, &'a str),
+}
+
+/// Run one of the `add`, `remove` or `mv` subcommands, preserving the file's formatting
+fn edit_file(ctx: &Context, patterns: &[String], edit: Edit<'_>) -> Result<()> {
+    let mut document = IgnoreDocument::load_from_file(&ctx.file)?;
+
+    for pattern in patterns {
+        let record = match edit {
+            Edit::Add(group) => {
+                let added = document.add_pattern_to_group(group, pattern)?;
+                if added {
+                    say!(ctx, "Added {} to [{}]", pattern, group);
+                } else {
+                    say!(ctx, "[{}] already contains {}", group, pattern);
+                }
+                EditRecord { action: "add", pattern, group: Some(group), changed: usize::from(added) }
+            }
+            Edit::Remove(group) => match document.remove_pattern(pattern, group) {
+                0 => anyhow::bail!("Pattern not found: {}", pattern),
+                count => {
+                    say!(ctx, "Removed {} ({} line(s))", pattern, count);
+                    EditRecord { action: "remove", pattern, group, changed: count }
+                }
+            },
+            Edit::Move(from, group) => {
+                document.move_pattern(pattern, from, group)?;
+                say!(ctx, "Moved {} to [{}]", pattern, group);
+                EditRecord { action: "move", pattern, group: Some(group), changed: 1 }
+            }
+        };
+        ctx.emit("edit", record)?;
+    }
+
+    document.save_to_file(&ctx.file)
 }
 
-fn convert_file(file_path: &str) {
-    let path = Path::new(file_path);
-    
-    if !path.exists() {
-        println!("El archivo no existe: {}", file_path);
-        process::exit(1);
+/// Run the `fmt` subcommand. Returns `false` if `--check` found unformatted files.
+fn format_files(ctx: &Context, files: &[PathBuf], style: &FormatStyle, check: bool) -> Result<bool> {
+    let mut all_formatted = true;
+
+    for file in ctx.files_or_default(files) {
+        let original = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let formatted = IgnoreDocument::parse(&original).format(style)
+            .with_context(|| file.display().to_string())?;
+
+        if formatted == original {
+            ctx.emit("format", FileRecord { file: &file, data: FormatRecord { changed: false, diff: None } })?;
+            continue;
+        }
+
+        let name = file.display().to_string();
+        let diff = if check {
+            all_formatted = false;
+            let diff = unified_diff(&name, &name, &original, &formatted);
+            say_raw!(ctx, "{}", diff);
+            Some(diff)
+        } else {
+            fs::write(&file, &formatted)?;
+            say!(ctx, "Formatted {}", name);
+            None
+        };
+        ctx.emit("format", FileRecord { file: &file, data: FormatRecord { changed: true, diff } })?;
     }
-    
-    match DotIgnore::convert_file(path, None::<&Path>) {
-        Ok(result) => {
-            println!("Conversión exitosa:");
-            println!("  Archivo fuente: {}", result.source_file.display());
-            println!("  Archivo destino: {}", result.destination_file.display());
-            println!("  Patrones convertidos: {}", result.pattern_count);
+
+    Ok(all_formatted)
+}
+
+/// Run the `migrate` subcommand. Returns `false` if `--check` found outdated files.
+fn migrate_files(ctx: &Context, files: &[PathBuf], check: bool) -> Result<bool> {
+    let mut all_current = true;
+
+    for file in ctx.files_or_default(files) {
+        let mut document = IgnoreDocument::load_from_file(&file)?;
+        let report = document.migrate()
+            .with_context(|| file.display().to_string())?;
+        ctx.emit("migration", FileRecord { file: &file, data: &report })?;
+
+        if report.is_empty() {
+            continue;
         }
-        Err(e) => {
-            println!("Error al convertir archivo: {}", e);
-            process::exit(1);
+
+        if check {
+            all_current = false;
+            say!(ctx, "{} needs migrating from format {} to {}", file.display(), report.from, report.to);
+        } else {
+            document.save_to_file(&file)?;
+            say!(ctx, "Migrated {} from format {} to {}", file.display(), report.from, report.to);
+        }
+        for change in &report.changes {
+            say!(ctx, "  {}", change);
         }
     }
+
+    Ok(all_current)
 }
 
-fn find_ignore_file(start_path: &Path) -> Option<std::path::PathBuf> {
-    let mut current_dir = if start_path.is_dir() {
-        start_path.to_path_buf()
+/// Run the `simplify` subcommand. Returns `false` if `--check` found redundant patterns.
+fn simplify_files(ctx: &Context, files: &[PathBuf], check: bool) -> Result<bool> {
+    let mut all_minimal = true;
+
+    for file in ctx.files_or_default(files) {
+        let mut document = IgnoreDocument::load_from_file(&file)?;
+        let report = document.simplify()
+            .with_context(|| file.display().to_string())?;
+        ctx.emit("simplify", FileRecord { file: &file, data: &report })?;
+
+        if report.is_empty() {
+            continue;
+        }
+
+        if check {
+            all_minimal = false;
+            say!(ctx, "{} has {} redundant pattern(s)", file.display(), report.removed.len());
+        } else {
+            document.save_to_file(&file)?;
+            say!(ctx, "Simplified {}: removed {} pattern(s)", file.display(), report.removed.len());
+        }
+        for redundancy in &report.removed {
+            say!(ctx, "  {}", redundancy);
+        }
+    }
+
+    Ok(all_minimal)
+}
+
+/// Run the `merge` subcommand; conflicts are reported on stderr
+fn merge_files(ctx: &Context, ours_path: &Path, theirs_path: &Path, destination: Option<PathBuf>, policy: MergePolicy) -> Result<()> {
+    let ours = ctx.load(ours_path)?;
+    let theirs = ctx.load(theirs_path)?;
+    let (merged, report) = ours.merge(&theirs, policy);
+
+    if ctx.output == OutputFormat::Text {
+        for conflict in &report.conflicts {
+            eprintln!("{}: {}", ctx.paint("conflict", Color::Yellow), conflict);
+        }
+    }
+
+    match &destination {
+        Some(destination) => {
+            let content = match StructuredFormat::from_path(destination) {
+                Some(format) => format.write(&merged)?,
+                None => merged.to_text(),
+            };
+            fs::write(destination, content)?;
+            say!(
+                ctx, "Merged {} and {} into {} ({} duplicate(s) removed, {} conflict(s))",
+                ours_path.display(), theirs_path.display(), destination.display(), report.duplicates, report.conflicts.len()
+            );
+        }
+        None => say_raw!(ctx, "{}", merged.to_text()),
+    }
+
+    ctx.emit("merge", MergeRecord {
+        destination_file: destination.as_deref(),
+        content: destination.is_none().then(|| merged.to_text()),
+        report: &report,
+    })
+}
+
+/// Run the `diff` subcommand
+fn diff_files(ctx: &Context, old: &Path, new: &Path) -> Result<()> {
+    let old = ctx.load(old)?;
+    let new = ctx.load(new)?;
+    let diff = old.diff(&new);
+
+    if diff.is_empty() {
+        say!(ctx, "No changes in meaning");
     } else {
-        start_path.parent()?.to_path_buf()
+        say_raw!(ctx, "{}", diff);
+    }
+
+    ctx.emit("diff", &diff)
+}
+
+/// Run the `impact` subcommand
+fn impact_report(ctx: &Context, old: &Path, new: &Path, tree: &Path) -> Result<()> {
+    let old = ctx.load(old)?;
+    let new = ctx.load(new)?;
+    let report = old.impact(&new, &ctx.scan(tree)?);
+
+    for entry in &report.entries {
+        let status = if entry.ignored { "ignored" } else { "tracked" };
+        let suffix = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir {
+            format!("{} file(s), {}", entry.files, format_bytes(entry.bytes))
+        } else {
+            format_bytes(entry.bytes)
+        };
+        say!(
+            ctx, "{} {}{} ({}): {} -> {}",
+            status, entry.path.display(), suffix, size,
+            entry.old_rule.as_deref().unwrap_or("no rule"),
+            entry.new_rule.as_deref().unwrap_or("no rule"),
+        );
+    }
+    say!(
+        ctx, "{} of {} path(s) change status: {} newly ignored, {} newly tracked",
+        report.entries.len(), report.scanned,
+        format_bytes(report.newly_ignored_bytes), format_bytes(report.newly_tracked_bytes),
+    );
+
+    ctx.emit("impact", &report)
+}
+
+/// Run the `equiv` subcommand. Returns `false` if the rule sets disagree on some path.
+fn equivalence_report(ctx: &Context, a: &Path, b: &Path, tree: &Path, paths: Option<&Path>) -> Result<bool> {
+    let left = ctx.load(a)?;
+    let right = ctx.load(b)?;
+    let tree = match paths {
+        Some(listing) => PathTree::from_listing(&fs::read_to_string(listing)?)
+            .with_context(|| format!("Failed to read path listing {}", listing.display()))?,
+        None => ctx.scan(tree)?,
     };
-    
-    loop {
-        let ignore_path = current_dir.join(".DotIgnore");
-        
-        if ignore_path.exists() {
-            return Some(ignore_path);
-        }
-        
-        match current_dir.parent() {
-            Some(parent) => current_dir = parent.to_path_buf(),
-            None => break,
-        }
-    }
-    
-    None
-} 
+    let report = left.equivalent_on(&right, &tree);
+
+    if report.is_equivalent() {
+        say!(ctx, "Equivalent on all {} path(s)", report.checked);
+    } else {
+        for counterexample in &report.counterexamples {
+            say!(ctx, "{}", counterexample);
+        }
+        say!(ctx, "{} of {} path(s) differ", report.counterexamples.len(), report.checked);
+    }
+
+    ctx.emit("equivalence", &report)?;
+    Ok(report.is_equivalent())
+}
+
+/// Run the `coverage` subcommand: a table of rules, the unused ones, and the busiest groups
+fn coverage_report(ctx: &Context, tree: &Path) -> Result<()> {
+    let dotignore = ctx.rules()?;
+    let report = dotignore.coverage(&ctx.scan(tree)?);
+
+    let names: Vec<String> = report.rules.iter().map(ToString::to_string).collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0).max("RULE".len());
+    say!(ctx, "{:<width$}  {:>7}  {:>7}  {:>10}", "RULE", "MATCHED", "FILES", "SIZE");
+    for (name, rule) in names.iter().zip(&report.rules) {
+        say!(ctx, "{:<width$}  {:>7}  {:>7}  {:>10}", name, rule.matched, rule.files, format_bytes(rule.bytes));
+    }
+
+    let unused: Vec<_> = report.unused().collect();
+    if !unused.is_empty() {
+        say!(ctx);
+        say!(ctx, "{} rule(s) matched nothing:", unused.len());
+        for rule in unused {
+            say!(ctx, "  {}", rule);
+        }
+    }
+
+    if !report.groups.is_empty() {
+        say!(ctx);
+        say!(ctx, "Groups by files ignored:");
+        for group in &report.groups {
+            say!(ctx, "  [{}] {} file(s), {}", group.name, group.files, format_bytes(group.bytes));
+        }
+    }
+    say!(ctx);
+    say!(ctx, "{} path(s) scanned", report.scanned);
+
+    ctx.emit("coverage", &report)
+}
+
+/// Run the `check-ignore` subcommand with the output and exit codes of `git check-ignore`.
+/// Returns `true` if at least one path was ignored (or, with `--verbose`, matched).
+fn check_ignore(ctx: &Context, args: &CheckIgnoreArgs) -> Result<bool> {
+    let CheckIgnoreArgs { paths, verbose, stdin, nul, non_matching, no_index: _ } = args;
+    let (quiet, verbose, nul, non_matching) = (ctx.quiet, *verbose, *nul, *non_matching);
+
+    if *stdin {
+        if !paths.is_empty() {
+            anyhow::bail!("cannot specify pathnames with --stdin");
+        }
+    } else if paths.is_empty() {
+        anyhow::bail!("no path specified");
+    }
+    if quiet && (verbose || (!*stdin && paths.len() != 1)) {
+        anyhow::bail!(if verbose { "cannot have both --quiet and --verbose" } else { "--quiet is only valid with a single pathname" });
+    }
+    if non_matching && !verbose {
+        anyhow::bail!("--non-matching is only valid with --verbose");
+    }
+
+    let (dotignore, source_map) = ctx.rules_with_source_map()?;
+    let source = ctx.file.display().to_string();
+
+    let mut out = std::io::stdout().lock();
+    let mut any_matched = false;
+    let mut check = |path: &str, out: &mut dyn Write| -> Result<()> {
+        // Como git, sin --verbose una negación cuenta como "no ignorado"
+        let rule = dotignore.matching_rule(path).filter(|rule| verbose || rule.is_ignored());
+        any_matched |= rule.is_some();
+        if ctx.output != OutputFormat::Text {
+            return ctx.emit("path", PathRecord::new(path, dotignore.matching_rule(path), &source_map));
+        }
+        if quiet || (rule.is_none() && !non_matching) {
+            return Ok(());
+        }
+
+        let shown_path = if nul { path.to_string() } else { quote_path(path) };
+        match (verbose, rule, nul) {
+            (false, _, false) => writeln!(out, "{}", shown_path)?,
+            (false, _, true) => write!(out, "{}\0", shown_path)?,
+            (true, rule, _) => {
+                let (file, line, pattern) = match rule {
+                    Some(rule) => (
+                        source.as_str(),
+                        source_map.line_of(&rule).map(|line| line.to_string()).unwrap_or_default(),
+                        rule.pattern.original.as_str(),
+                    ),
+                    None => ("", String::new(), ""),
+                };
+                if nul {
+                    write!(out, "{}\0{}\0{}\0{}\0", file, line, pattern, shown_path)?;
+                } else {
+                    writeln!(out, "{}:{}:{}\t{}", file, line, pattern, shown_path)?;
+                }
+            }
+        }
+        Ok(())
+    };
+
+    if *stdin {
+        // Responder a cada ruta en cuanto llega, para herramientas que mantienen el proceso abierto
+        let separator = if nul { b'\0' } else { b'\n' };
+        for path in std::io::stdin().lock().split(separator) {
+            let path = String::from_utf8(path?)?;
+            let path = if nul { path.as_str() } else { path.trim_end_matches('\r') };
+            check(path, &mut out)?;
+            out.flush()?;
+        }
+    } else {
+        for path in paths {
+            check(path, &mut out)?;
+        }
+    }
+
+    Ok(any_matched)
+}
+
+/// Quote a path the way git does with `core.quotePath` enabled
+fn quote_path(path: &str) -> String {
+    let needs_quoting = path.bytes().any(|b| !(0x20..0x7f).contains(&b) || b == b'"' || b == b'\\');
+    if !needs_quoting {
+        return path.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for byte in path.bytes() {
+        match byte {
+            b'\x07' => quoted.push_str("\\a"),
+            b'\x08' => quoted.push_str("\\b"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\x0b' => quoted.push_str("\\v"),
+            b'\x0c' => quoted.push_str("\\f"),
+            b'\r' => quoted.push_str("\\r"),
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            0x20..=0x7e => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Run the `lint` subcommand. Returns `false` if a rule set to `deny` found something.
+fn lint_files(ctx: &Context, files: &[PathBuf], config: Option<&Path>, format: &str) -> Result<bool> {
+    let github = match format {
+        "text" => false,
+        "github" => true,
+        other => anyhow::bail!("Unknown output format: {} (expected text or github)", other),
+    };
+    let explicit_config = config.map(LintConfig::load).transpose()?;
+    let mut passed = true;
+
+    for file in ctx.files_or_default(files) {
+        let config = match &explicit_config {
+            Some(config) => config.clone(),
+            None => {
+                let candidate = file.with_file_name(lint::CONFIG_FILE_NAME);
+                if candidate.exists() { LintConfig::load(candidate)? } else { LintConfig::default() }
+            }
+        };
+
+        // Structured files have no line numbers to report
+        let diagnostics = if StructuredFormat::from_path(&file).is_some() {
+            lint::run_with(&DotIgnore::load_from_file(&file)?, &config)
+        } else {
+            let document = IgnoreDocument::load_from_file(&file)?;
+            lint::run_document(&document, &config).with_context(|| file.display().to_string())?
+        };
+
+        for diagnostic in &diagnostics {
+            passed &= diagnostic.severity != Severity::Error;
+            if github {
+                let level = if diagnostic.severity == Severity::Error { "error" } else { "warning" };
+                let line = diagnostic.line.map(|line| format!(",line={}", line)).unwrap_or_default();
+                let title = diagnostic.code.as_deref().unwrap_or("ignore");
+                say!(ctx, "::{} file={}{},title={}::{}", level, file.display(), line, title, diagnostic.message);
+            } else {
+                // file:line: prefix, understood by most editors and CI log parsers
+                say!(ctx, "{}", ctx.diagnostic(ctx.location(&file, diagnostic), diagnostic));
+            }
+            ctx.emit("diagnostic", FileRecord { file: &file, data: diagnostic })?;
+        }
+    }
+
+    Ok(passed)
+}
+
+/// Format a count with thousands separators, as in `12,304`
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Format a file count, as in `1 file` or `12,304 files`
+fn format_file_count(count: usize) -> String {
+    format!("{} {}", format_count(count), if count == 1 { "file" } else { "files" })
+}
+
+/// Format a byte count with a binary unit, as used by `size:` conditions
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
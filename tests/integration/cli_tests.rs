@@ -0,0 +1,117 @@
+use ignore::DotIgnore;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::tempdir;
+
+// Pruebas del binario `ignore`
+
+/// Run `ignore` in `dir` with `input` on stdin and return its exit code and stdout
+fn run(dir: &Path, args: &[&str], input: &str) -> (Option<i32>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ignore"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    (output.status.code(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn test_check_ignore_quiet_with_stdin() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".ignore"), "*.log\n").unwrap();
+
+    let (code, stdout) = run(dir.path(), &["check-ignore", "--quiet", "--stdin"], "src/main.rs\napp.log\n");
+    assert_eq!(code, Some(0));
+    assert!(stdout.is_empty());
+
+    let (code, _) = run(dir.path(), &["check-ignore", "--quiet", "--stdin"], "src/main.rs\n");
+    assert_eq!(code, Some(1));
+
+    // Sin --stdin, --quiet sigue necesitando una sola ruta
+    let (code, _) = run(dir.path(), &["check-ignore", "--quiet", "a.log", "b.log"], "");
+    assert_eq!(code, Some(128));
+}
+
+#[test]
+fn test_convert_gitignore_to_structured() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".gitignore"), "*.log\n!keep.log\n").unwrap();
+
+    let (code, _) = run(dir.path(), &["convert", ".gitignore", "-d", "rules.toml"], "");
+    assert_eq!(code, Some(0));
+    let (code, _) = run(dir.path(), &["convert", ".gitignore", "--to", "yaml"], "");
+    assert_eq!(code, Some(0));
+
+    for file in ["rules.toml", ".ignore.yaml"] {
+        let dotignore = DotIgnore::load_from_file(dir.path().join(file)).unwrap();
+        assert!(dotignore.is_ignored("app.log") && !dotignore.is_ignored("keep.log"), "{}", file);
+    }
+}
+
+#[test]
+fn test_exit_statuses() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".ignore"), "[logs] {\n*.log\n}\n").unwrap();
+    fs::write(dir.path().join("app.log"), "").unwrap();
+
+    // 0: todo bien, 1: la comprobación falla
+    assert_eq!(run(dir.path(), &["check", "app.log"], "").0, Some(0));
+    assert_eq!(run(dir.path(), &["check", "src/main.rs"], "").0, Some(1));
+    assert_eq!(run(dir.path(), &["fmt", "--check"], "").0, Some(1));
+    assert_eq!(run(dir.path(), &["fmt"], "").0, Some(0));
+    assert_eq!(run(dir.path(), &["fmt", "--check"], "").0, Some(0));
+    assert_eq!(run(dir.path(), &["convert", ".ignore"], "").0, Some(0));
+
+    // 2: uso incorrecto; clean exige --dry-run o --force y no borra nada
+    assert_eq!(run(dir.path(), &["clean"], "").0, Some(2));
+    assert!(dir.path().join("app.log").exists());
+    assert_eq!(run(dir.path(), &["clean", "--dry-run"], "").0, Some(0));
+    assert!(dir.path().join("app.log").exists());
+
+    // 3: no se pudo ejecutar
+    assert_eq!(run(dir.path(), &["--file", "missing", "check", "app.log"], "").0, Some(3));
+}
+
+#[test]
+fn test_json_records() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join(".ignore"), "# Logs\n*.log\n").unwrap();
+    let records = |args: &[&str]| -> Vec<serde_json::Value> {
+        let (_, stdout) = run(dir.path(), args, "");
+        serde_json::from_str(&stdout).unwrap()
+    };
+
+    let check = records(&["--output", "json", "check", "app.log", "src/main.rs"]);
+    assert_eq!(check, [
+        serde_json::json!({"version": 1, "command": "check", "type": "path", "path": "app.log", "ignored": true, "pattern": "*.log", "line": 2}),
+        serde_json::json!({"version": 1, "command": "check", "type": "path", "path": "src/main.rs", "ignored": false}),
+    ]);
+
+    let convert = records(&["--output", "json", "convert", ".ignore"]);
+    assert_eq!(convert, [
+        serde_json::json!({"version": 1, "command": "convert", "type": "diagnostic", "file": ".ignore", "severity": "warning", "line": 1, "message": "1 comment line(s) are not carried over"}),
+        serde_json::json!({
+            "version": 1, "command": "convert", "type": "conversion", "source_file": ".ignore", "destination_file": ".ignore.toml",
+            "pattern_count": 1, "standard_patterns": 1, "platform_patterns": {},
+        }),
+    ]);
+
+    let error = records(&["--output", "json", "--file", "missing", "check", "app.log"]);
+    assert_eq!(error.len(), 1);
+    assert_eq!(error[0]["type"], "error");
+    assert_eq!(error[0]["command"], "check");
+    assert!(error[0]["message"].as_str().unwrap().contains("missing"));
+
+    // ndjson: un registro por línea
+    let (code, stdout) = run(dir.path(), &["--output", "ndjson", "fmt", "--check"], "");
+    assert_eq!(code, Some(0));
+    let lines: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines, [serde_json::json!({"version": 1, "command": "fmt", "type": "format", "file": ".ignore", "changed": false})]);
+}
@@ -1,3 +1,6 @@
 // Pruebas de integración para DotIgnore
 
 mod complete_workflow_tests; 
+
+#[cfg(feature = "cli")]
+mod cli_tests;
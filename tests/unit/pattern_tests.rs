@@ -48,3 +48,15 @@ fn test_size_condition_pattern() {
     assert!(pattern.matches_with_size("app.log", 300));
     assert!(!pattern.matches_with_size("app.log", 1000));
 }
+
+#[test]
+fn test_escaped_pattern() {
+    let pattern = Pattern::new("\\!important.txt");
+    assert!(!pattern.is_negated());
+    assert_eq!(pattern.original, "\\!important.txt");
+    assert!(pattern.matches("!important.txt"));
+    assert!(!pattern.matches("important.txt"));
+    
+    let pattern = Pattern::new("\\@profile dev");
+    assert!(pattern.matches("@profile dev"));
+}